@@ -0,0 +1,292 @@
+//! The log of the requests made to the update server, see [`PrivUpdaterBuilder::warp_log`] and [`PrivUpdaterBuilder::access_log`]
+//!
+//! The requests are logged around the whole `warp` service rather than through `warp::log`, whose `Info` neither
+//! knows the size of the response nor the client address of a server `warp` doesn't run itself.
+
+use super::*;
+use std::{
+    fs::File,
+    io::Write,
+    time::{ Instant, SystemTime },
+};
+use warp::http::{ Request, header };
+
+/// The format of the logged requests, see [`PrivUpdaterBuilder::warp_log_format`]
+#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// The Common Log Format of Apache: `127.0.0.1 - - [01/May/2024:12:34:56 +0000] "GET /latest.json HTTP/1.1" 200 1234`
+    #[default]
+    Common,
+    /// The Common Log Format followed by the quoted `Referer` and `User-Agent`, as Apache's `combined`
+    Combined,
+    /// Each `{field}` replaced by its value, e.g. `{method} {path} {status} {elapsed_ms}ms`
+    ///
+    /// The fields are `method`, `path`, `query`, `version`, `status`, `elapsed` (e.g. `1.2ms`), `elapsed_ms`,
+    /// `bytes` (the response `Content-Length`), `remote_addr` (the client IP), `user_agent`, `referer` and `time`
+    /// (as in the Common Log Format). Missing values are logged as `-`, unknown fields are left as is.
+    Custom(String),
+}
+
+/// The format of the [`PrivUpdaterBuilder::access_log`] file, see [`PrivUpdaterBuilder::access_log_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// An [`AccessLogEntry`] as JSON per line, for the log aggregators
+    #[default]
+    JsonLines,
+    /// The Combined Log Format of Apache, as [`LogFormat::Combined`]
+    CombinedLog,
+    /// The fields of the [`AccessLogEntry`] in their order, separated by the given delimiter, without a header line
+    ///
+    /// The fields containing the delimiter, a quote or a newline are quoted, missing values are empty.
+    Csv(char),
+}
+
+/// A request written to the [`PrivUpdaterBuilder::access_log`] file
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AccessLogEntry {
+    /// When the request was received, ISO 8601 in UTC with milliseconds
+    pub time:		String,
+    /// The IP address of the client
+    pub remote_addr:	String,
+    /// e.g. `GET`
+    pub method:		String,
+    /// Without the query
+    pub path:		String,
+    /// Without the `?`
+    pub query:		Option<String>,
+    /// e.g. `HTTP/1.1`
+    pub version:	String,
+    /// Of the response
+    pub status:		u16,
+    /// The `Content-Length` of the response
+    pub bytes:		Option<u64>,
+    /// Until the response headers were ready, the body may take longer
+    pub elapsed_ms:	u64,
+    /// The `Referer` of the request
+    pub referer:	Option<String>,
+    /// The `User-Agent` of the request
+    pub user_agent:	Option<String>,
+}
+
+/// Where and how the requests are logged
+pub(crate) struct AccessLog {
+    /// of the `log` records, see [`PrivUpdaterBuilder::warp_log`]
+    pub(crate) target:	Option<String>,
+    pub(crate) format:	LogFormat,
+    pub(crate) file:	Option<AccessLogFile>,
+}
+
+/// The file every request is appended to, see [`PrivUpdaterBuilder::access_log`]
+pub(crate) struct AccessLogFile {
+    file:	Mutex<File>,
+    format:	AccessLogFormat,
+}
+
+impl AccessLogFile {
+    /// Opens `path` for appending, creating it if absent
+    pub(crate) fn open(path: &Path, format: AccessLogFormat) -> std::io::Result<Self> {
+	let file = File::options().create(true).append(true).open(path)?;
+	Ok( Self { file: Mutex::new(file), format } )
+    }
+    fn write(&self, line: &str) {
+	// a single write per line, so that the lines of concurrent requests don't interleave
+	if let Err(_e) = self.file.lock().unwrap().write_all(format!("{line}\n").as_bytes()) {
+	    #[cfg(feature = "tracing")]
+	    tracing::warn!(error = %_e, "failed to write the access log");
+	}
+    }
+}
+
+impl AccessLog {
+    /// Starts timing `request`, logged once its response is [`finish`](LogEntry::finish)ed
+    pub(crate) fn start(self: &Arc<Self>, request: &Request<Body>, remote_addr: SocketAddr) -> LogEntry {
+	let header = |name| request.headers().get(name).and_then(|value: &warp::http::HeaderValue| value.to_str().ok()).map(str::to_string);
+	LogEntry {
+	    log:		self.clone(),
+	    method:		request.method().to_string(),
+	    path:		request.uri().path().to_string(),
+	    query:		request.uri().query().map(str::to_string),
+	    version:		format!("{:?}", request.version()),
+	    remote_addr,
+	    referer:		header(header::REFERER),
+	    user_agent:		header(header::USER_AGENT),
+	    time:		SystemTime::now(),
+	    started:		Instant::now(),
+	}
+    }
+}
+
+/// A request being answered
+pub(crate) struct LogEntry {
+    log:		Arc<AccessLog>,
+    method:		String,
+    path:		String,
+    query:		Option<String>,
+    version:		String,
+    remote_addr:	SocketAddr,
+    referer:		Option<String>,
+    user_agent:		Option<String>,
+    time:		SystemTime,
+    started:		Instant,
+}
+
+impl LogEntry {
+    pub(crate) fn finish(self, response: &Response<Body>) {
+	let elapsed = self.started.elapsed();
+	if let Some(file) = &self.log.file {
+	    file.write(&match file.format {
+		AccessLogFormat::JsonLines	=> serde_json::to_string(&self.entry(response, elapsed)).expect("the entries are always serializable"),
+		AccessLogFormat::CombinedLog	=> self.formatted(&LogFormat::Combined, response, elapsed),
+		AccessLogFormat::Csv(delimiter)	=> csv_line(&self.entry(response, elapsed), delimiter),
+	    });
+	}
+	let Some(target) = self.log.target.as_deref() else {
+	    return;
+	};
+	let line = self.line(response, elapsed);
+	#[cfg(not(feature = "tracing"))]
+	log::info!(target: target, "{line}");
+	#[cfg(feature = "tracing")]
+	tracing::info!(
+	    log_target = target,
+	    method = self.method,
+	    path = self.path,
+	    status = response.status().as_u16(),
+	    elapsed = ?elapsed,
+	    "{line}",
+	);
+    }
+    pub(crate) fn entry(&self, response: &Response<Body>, elapsed: Duration) -> AccessLogEntry {
+	AccessLogEntry {
+	    time:		iso_8601_time(self.time),
+	    remote_addr:	self.remote_addr.ip().to_string(),
+	    method:		self.method.clone(),
+	    path:		self.path.clone(),
+	    query:		self.query.clone(),
+	    version:		self.version.clone(),
+	    status:		response.status().as_u16(),
+	    bytes:		response.headers()
+		.get(header::CONTENT_LENGTH)
+		.and_then(|value| value.to_str().ok()?.parse().ok()),
+	    elapsed_ms:		u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+	    referer:		self.referer.clone(),
+	    user_agent:		self.user_agent.clone(),
+	}
+    }
+    /// In the [`LogFormat`] of the [`warp_log`](PrivUpdaterBuilder::warp_log)
+    pub(crate) fn line(&self, response: &Response<Body>, elapsed: Duration) -> String {
+	self.formatted(&self.log.format, response, elapsed)
+    }
+    fn formatted(&self, format: &LogFormat, response: &Response<Body>, elapsed: Duration) -> String {
+	let bytes = response.headers()
+	    .get(header::CONTENT_LENGTH)
+	    .and_then(|value| value.to_str().ok())
+	    .map(str::to_string);
+	let request_line = match &self.query {
+	    Some(query)	=> format!("{} {}?{query} {}", self.method, self.path, self.version),
+	    None	=> format!("{} {} {}", self.method, self.path, self.version),
+	};
+	let common = format!(
+	    "{} - - [{}] \"{request_line}\" {} {}",
+	    self.remote_addr.ip(),
+	    common_log_time(self.time),
+	    response.status().as_u16(),
+	    bytes.as_deref().filter(|&bytes| bytes != "0").unwrap_or("-"),
+	);
+	match format {
+	    LogFormat::Common		=> common,
+	    LogFormat::Combined		=> format!(
+		"{common} \"{}\" \"{}\"",
+		self.referer.as_deref().unwrap_or("-"),
+		self.user_agent.as_deref().unwrap_or("-"),
+	    ),
+	    LogFormat::Custom(template)	=> render(template, |field| Some(match field {
+		"method"	=> self.method.clone(),
+		"path"		=> self.path.clone(),
+		"query"		=> self.query.clone().unwrap_or_else(|| String::from("-")),
+		"version"	=> self.version.clone(),
+		"status"	=> response.status().as_u16().to_string(),
+		"elapsed"	=> format!("{elapsed:?}"),
+		"elapsed_ms"	=> elapsed.as_millis().to_string(),
+		"bytes"		=> bytes.clone().unwrap_or_else(|| String::from("-")),
+		"remote_addr"	=> self.remote_addr.ip().to_string(),
+		"user_agent"	=> self.user_agent.clone().unwrap_or_else(|| String::from("-")),
+		"referer"	=> self.referer.clone().unwrap_or_else(|| String::from("-")),
+		"time"		=> common_log_time(self.time),
+		_		=> return None,
+	    })),
+	}
+    }
+}
+
+/// Replaces the `{field}`s of `template` for which `value` returns something
+fn render(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut line = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+	line.push_str(&rest[..start]);
+	rest = &rest[start + 1..];
+	match rest.split_once('}').and_then(|(field, after)| Some(( value(field)?, after ))) {
+	    Some((value, after))	=> { line.push_str(&value); rest = after; },
+	    None			=> line.push('{'),
+	}
+    }
+    line.push_str(rest);
+    line
+}
+
+/// The fields of `entry` separated by `delimiter`, quoted when they contain it
+pub(crate) fn csv_line(entry: &AccessLogEntry, delimiter: char) -> String {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    [
+	entry.time.clone(),
+	entry.remote_addr.clone(),
+	entry.method.clone(),
+	entry.path.clone(),
+	optional(&entry.query),
+	entry.version.clone(),
+	entry.status.to_string(),
+	entry.bytes.map(|bytes| bytes.to_string()).unwrap_or_default(),
+	entry.elapsed_ms.to_string(),
+	optional(&entry.referer),
+	optional(&entry.user_agent),
+    ]
+	.iter()
+	.map(|field| if field.contains([delimiter, '"', '\n', '\r']) {
+	    format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+	    field.clone()
+	})
+	.collect::<Vec<_>>()
+	.join(&delimiter.to_string())
+}
+
+/// The UTC date and time of `time`, as `(year, month, day, hour, minute, second)` with the months starting at 0
+fn utc_fields(time: SystemTime) -> (u64, usize, u64, u64, u64, u64) {
+    let seconds = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+    // the inverse of `release::parse_timestamp`, with the years starting in March
+    let days = seconds / 86_400 + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let (year, month) = if month < 10 { (era * 400 + year_of_era, month + 2) } else { (era * 400 + year_of_era + 1, month - 10) };
+    (year, month as usize, day, seconds % 86_400 / 3_600, seconds % 3_600 / 60, seconds % 60)
+}
+
+/// `01/May/2024:12:34:56 +0000`, always in UTC
+pub(crate) fn common_log_time(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let (year, month, day, hour, minute, second) = utc_fields(time);
+    format!("{day:02}/{}/{year}:{hour:02}:{minute:02}:{second:02} +0000", MONTHS[month])
+}
+
+/// `2024-05-01T12:34:56.789Z`
+pub(crate) fn iso_8601_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc_fields(time);
+    let millis = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().subsec_millis();
+    format!("{year}-{:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{millis:03}Z", month + 1)
+}
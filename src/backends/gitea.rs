@@ -0,0 +1,79 @@
+//! The releases of a Gitea (or Forgejo) repository
+
+use super::*;
+
+#[derive(Deserialize)]
+struct GiteaRelease {
+    tag_name:		String,
+    published_at:	Option<String>,
+    assets:		Vec<GiteaAsset>,
+}
+
+#[derive(Deserialize)]
+struct GiteaAsset {
+    name:			String,
+    browser_download_url:	String,
+    #[serde(default)]
+    size:			u64,
+    /// attachments can't be replaced, only deleted and uploaded again
+    #[serde(default)]
+    created_at:			String,
+}
+
+/// The latest release of a repository on a Gitea or Forgejo instance, e.g. Codeberg
+pub struct GiteaBackend {
+    base_url:	String,
+    owner:	String,
+    repo:	String,
+    headers:	HeaderMap,
+}
+
+impl GiteaBackend {
+    /// The latest release of `owner/repo` on the instance at `base_url` (e.g. `https://codeberg.org`), read with `token`
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the token can't be sent in a header.
+    pub fn new(base_url: &str, owner: &str, repo: &str, token: &str) -> Result<Self, Box<dyn Error>> {
+	Ok( Self {
+	    base_url:	base_url.trim_end_matches('/').to_string(),
+	    owner:	owner.to_string(),
+	    repo:	repo.to_string(),
+	    headers:	credential_headers(AUTHORIZATION, &format!("token {token}"))?,
+	} )
+    }
+}
+
+impl Backend for GiteaBackend {
+    fn latest_release_url(&self) -> String {
+	format!("{}/api/v1/repos/{}/{}/releases/latest", self.base_url, self.owner, self.repo)
+    }
+    fn api_headers(&self) -> HeaderMap {
+	self.headers.clone()
+    }
+    /// The attachments are downloaded from their release page URL, which takes the same credentials
+    fn download_headers(&self) -> HeaderMap {
+	self.headers.clone()
+    }
+    fn parse_release(&self, body: &[u8]) -> Result<BackendRelease, Box<dyn Error + Send + Sync>> {
+	let GiteaRelease { tag_name, published_at, assets } = serde_json::from_slice(body)?;
+	Ok( BackendRelease {
+	    tag_name,
+	    published_at,
+	    assets:	assets
+		.into_iter()
+		.map(|GiteaAsset { name, browser_download_url, size, created_at }| AssetInfo {
+		    name,
+		    url:			browser_download_url.clone(),
+		    browser_download_url,
+		    size,
+		    updated_at:			created_at,
+		    ..Default::default()
+		})
+		.collect(),
+	} )
+    }
+    fn missing_permission_hint(&self) -> String {
+	format!("the token needs the `read:repository` scope to read the releases of {}/{}", self.owner, self.repo)
+    }
+}
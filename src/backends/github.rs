@@ -0,0 +1,153 @@
+//! The releases of a GitHub repository, the default [`Backend`]
+
+use super::*;
+
+/// Root of the GitHub REST API, see [`PrivUpdaterBuilder::github_api_base`]
+pub(crate) const GITHUB_API: &str = "https://api.github.com";
+
+#[derive(Deserialize)]
+struct GitHubAssetsList {
+    tag_name:	String,
+    published_at:	Option<String>,
+    assets:	Vec<GitHubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GitHubAsset {
+    name:			String,
+    url:			String,
+    browser_download_url:	String,
+    #[serde(default)]
+    size:			u64,
+    #[serde(default)]
+    content_type:		String,
+    #[serde(default)]
+    label:			Option<String>,
+    #[serde(default)]
+    updated_at:			String,
+}
+
+/// The latest release of a GitHub repository, what [`PrivUpdaterBuilder::new`] fetches
+///
+/// The assets are downloaded through the API (`application/octet-stream`), which works for private repositories.
+pub struct GitHubBackend {
+    api_base:			String,
+    gh_account_name:		String,
+    gh_repo_name:		String,
+    headers:			HeaderMap,
+    missing_permission_hint:	String,
+}
+
+impl GitHubBackend {
+    /// The latest release of `gh_account_name/gh_repo_name`, read with `gh_token`
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the token can't be sent in a header.
+    pub fn new<D: std::fmt::Display>(gh_account_name: D, gh_repo_name: D, gh_token: D) -> Result<Self, Box<dyn Error>> {
+	Self::configured(gh_account_name.to_string(), gh_repo_name.to_string(), &gh_token.to_string(), GITHUB_API, None, false)
+    }
+    /// Root of the GitHub REST API (default: `https://api.github.com`), see [`PrivUpdaterBuilder::github_api_base`]
+    pub fn api_base(mut self, url: &str) -> Self {
+	self.api_base = url.trim_end_matches('/').to_string();
+	self
+    }
+    /// The backend of the [`PrivUpdaterBuilder`] settings
+    pub(crate) fn configured(
+	gh_account_name:	String,
+	gh_repo_name:		String,
+	gh_token:		&str,
+	api_base:		&str,
+	user_agent:		Option<String>,
+	legacy_accept_header:	bool,
+    ) -> Result<Self, Box<dyn Error>> {
+	let mut headers = api_headers(&gh_account_name, &gh_repo_name, gh_token, user_agent)?;
+	headers.insert(ACCEPT, HeaderValue::from_static(if legacy_accept_header { "application/vnd.github.v3+json" } else { "application/vnd.github+json" }));
+	Ok( Self {
+	    api_base:			api_base.trim_end_matches('/').to_string(),
+	    missing_permission_hint:	missing_permission_hint(gh_token, &gh_account_name, &gh_repo_name),
+	    gh_account_name,
+	    gh_repo_name,
+	    headers,
+	} )
+    }
+    pub(crate) fn api_base_url(&self) -> &str {
+	&self.api_base
+    }
+    pub(crate) fn user_agent(&self) -> &HeaderValue {
+	&self.headers[USER_AGENT]
+    }
+}
+
+impl Backend for GitHubBackend {
+    fn latest_release_url(&self) -> String {
+	format!("{}/repos/{}/{}/releases/latest", self.api_base, self.gh_account_name, self.gh_repo_name)
+    }
+    fn api_headers(&self) -> HeaderMap {
+	self.headers.clone()
+    }
+    fn download_headers(&self) -> HeaderMap {
+	let mut headers = self.headers.clone();
+	headers.insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+	headers
+    }
+    fn parse_release(&self, body: &[u8]) -> Result<BackendRelease, Box<dyn Error + Send + Sync>> {
+	let GitHubAssetsList { tag_name, published_at, assets } = serde_json::from_slice(body)?;
+	Ok( BackendRelease {
+	    tag_name,
+	    published_at,
+	    assets:	assets
+		.into_iter()
+		.map(|GitHubAsset { name, url, browser_download_url, size, content_type, label, updated_at }| AssetInfo {
+		    name,
+		    url,
+		    browser_download_url,
+		    size,
+		    content_type,
+		    // GitHub sends an empty label rather than none
+		    label:	label.filter(|label| !label.is_empty()),
+		    updated_at,
+		})
+		.collect(),
+	} )
+    }
+    fn missing_permission_hint(&self) -> String {
+	self.missing_permission_hint.clone()
+    }
+    /// `GET /rate_limit` doesn't count against the rate limit
+    fn health_check_url(&self) -> String {
+	format!("{}/rate_limit", self.api_base)
+    }
+}
+
+/// Headers sent with every request to GitHub
+fn api_headers(gh_account_name: &str, gh_repo_name: &str, gh_token: &str, user_agent: Option<String>) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = credential_headers(AUTHORIZATION, &format!("Bearer {gh_token}"))?;
+    headers.insert(HeaderName::from_static("x-github-api-version"), HeaderValue::from_static( "2022-11-28" ) );
+    let user_agent = user_agent.unwrap_or_else(|| format!("priv-tauri-updater/{} ({gh_repo_name}/{gh_account_name})", env!("CARGO_PKG_VERSION")));
+    if user_agent.contains(['\r', '\n']) {
+	return Err(Box::new(PrivUpdaterError::InvalidUserAgent(user_agent)));
+    }
+    headers.insert(USER_AGENT,  HeaderValue::from_str( &user_agent )?);
+    Ok( headers )
+}
+
+/// Checks that the GitHub API at `api_base` answers at all, unauthenticated so that an invalid token doesn't count as unreachable
+pub(crate) async fn preflight_check(client: &Client, api_base: &str, user_agent: &HeaderValue) -> Result<(), PrivUpdaterError> {
+    client.get(api_base)
+	.header(USER_AGENT, user_agent.clone())
+	.send()
+	.await
+	.and_then(reqwest::Response::error_for_status)
+	.map(|_| ())
+	.map_err(|source| PrivUpdaterError::GitHubUnreachable { url: api_base.to_string(), source })
+}
+
+/// Fine-grained PATs don't report their scopes (no `X-OAuth-Scopes` header), so the hint depends on the token prefix
+fn missing_permission_hint(gh_token: &str, gh_account_name: &str, gh_repo_name: &str) -> String {
+    if gh_token.starts_with("github_pat_") {
+	format!("grant the fine-grained personal access token the `Contents: Read` repository permission on {gh_account_name}/{gh_repo_name}")
+    } else {
+	format!("the token needs the `repo` scope to read the releases of {gh_account_name}/{gh_repo_name}")
+    }
+}
@@ -0,0 +1,85 @@
+//! The releases of a GitLab project
+
+use super::*;
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name:		String,
+    released_at:	Option<String>,
+    assets:		GitLabAssets,
+}
+
+#[derive(Deserialize)]
+struct GitLabAssets {
+    links:	Vec<GitLabLink>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLink {
+    name:		String,
+    url:		String,
+    direct_asset_url:	Option<String>,
+}
+
+/// The latest release of a GitLab project, with its assets attached as release links
+///
+/// The source code archives GitLab adds to every release aren't served. The links have no size nor content type.
+pub struct GitLabBackend {
+    base_url:	String,
+    project:	String,
+    headers:	HeaderMap,
+}
+
+impl GitLabBackend {
+    /// The latest release of `project` on gitlab.com, read with a token having the `read_api` scope
+    ///
+    /// `project` is the path of the project (`group/project`) or its numeric ID.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the token can't be sent in a header.
+    pub fn new(project: &str, token: &str) -> Result<Self, Box<dyn Error>> {
+	Ok( Self {
+	    base_url:	String::from("https://gitlab.com"),
+	    project:	project.to_string(),
+	    headers:	credential_headers(HeaderName::from_static("private-token"), token)?,
+	} )
+    }
+    /// Root of a self-managed instance, e.g. `https://gitlab.example.com` (default: `https://gitlab.com`)
+    pub fn base_url(mut self, url: &str) -> Self {
+	self.base_url = url.trim_end_matches('/').to_string();
+	self
+    }
+}
+
+impl Backend for GitLabBackend {
+    /// The `permalink/latest` of GitLab 15.4 or later
+    fn latest_release_url(&self) -> String {
+	format!("{}/api/v4/projects/{}/releases/permalink/latest", self.base_url, self.project.replace('/', "%2F"))
+    }
+    fn api_headers(&self) -> HeaderMap {
+	self.headers.clone()
+    }
+    fn download_headers(&self) -> HeaderMap {
+	self.headers.clone()
+    }
+    fn parse_release(&self, body: &[u8]) -> Result<BackendRelease, Box<dyn Error + Send + Sync>> {
+	let GitLabRelease { tag_name, released_at, assets } = serde_json::from_slice(body)?;
+	Ok( BackendRelease {
+	    tag_name,
+	    published_at:	released_at,
+	    assets:		assets.links
+		.into_iter()
+		.map(|GitLabLink { name, url, direct_asset_url }| AssetInfo {
+		    name,
+		    url:			direct_asset_url.unwrap_or_else(|| url.clone()),
+		    browser_download_url:	url,
+		    ..Default::default()
+		})
+		.collect(),
+	} )
+    }
+    fn missing_permission_hint(&self) -> String {
+	format!("the token needs the `read_api` scope to read the releases of {}", self.project)
+    }
+}
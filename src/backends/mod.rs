@@ -0,0 +1,84 @@
+//! Where the release is fetched from: GitHub by default, or a GitLab or Gitea instance, see [`PrivUpdaterBuilder::backend`]
+//!
+//! A [`Backend`] only describes the requests to its API and parses the answer, the requests themselves are made by
+//! the updater. This way every backend gets the conditional requests of [`PrivUpdater::refresh`], the
+//! [`PrivUpdaterBuilder::api_response_max_bytes`] and the `middleware` stack.
+
+use super::*;
+
+mod gitea;
+mod github;
+mod gitlab;
+
+pub use gitea::GiteaBackend;
+pub use github::GitHubBackend;
+pub(crate) use github::{ GITHUB_API, preflight_check };
+pub use gitlab::GitLabBackend;
+
+/// A service hosting the releases of the app, see [`PrivUpdaterBuilder::backend`]
+pub trait Backend: Send + Sync {
+    /// URL of the latest release in the backend API
+    fn latest_release_url(&self) -> String;
+    /// Headers of the requests to the backend API, with the credentials
+    fn api_headers(&self) -> HeaderMap;
+    /// Headers of the asset downloads, with the credentials
+    fn download_headers(&self) -> HeaderMap;
+    /// Parses the answer to the request of the [`latest_release_url`](Self::latest_release_url)
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `body` isn't a release of the backend.
+    fn parse_release(&self, body: &[u8]) -> Result<BackendRelease, Box<dyn Error + Send + Sync>>;
+    /// Explains a `403 Forbidden` of the backend API, see [`PrivUpdaterError::InsufficientPermissions`]
+    fn missing_permission_hint(&self) -> String {
+	String::from("the token isn't allowed to read the releases")
+    }
+    /// URL requested by [`UpdateServerHandle::full_health_check`] (default: the [`latest_release_url`](Self::latest_release_url))
+    fn health_check_url(&self) -> String {
+	self.latest_release_url()
+    }
+}
+
+/// The latest release of a [`Backend`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendRelease {
+    /// The tag of the release, a different one means a new release
+    pub tag_name:	String,
+    /// ISO 8601, `None` if unknown or for a draft
+    pub published_at:	Option<String>,
+    /// The assets of the release, served under their name
+    pub assets:		Vec<AssetInfo>,
+}
+
+/// An asset of a [`BackendRelease`]
+///
+/// The backends that don't know some of the details leave them at their default:
+/// `AssetInfo { name, url, browser_download_url, ..Default::default() }`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize)]
+pub struct AssetInfo {
+    /// The file name, which is also the path it's served at
+    pub name:			String,
+    /// The URL the asset is downloaded from with the [`Backend::download_headers`]
+    pub url:			String,
+    /// The URL of the asset on the release page, which `latest.json` links to,
+    /// see [`PrivUpdaterBuilder::use_browser_download_url`]
+    pub browser_download_url:	String,
+    /// In bytes, `0` if unknown; a download of another size fails
+    pub size:			u64,
+    /// The type the asset was uploaded with, served instead of the `application/octet-stream` of the download; empty if unknown
+    pub content_type:		String,
+    /// The display name on the release page
+    pub label:			Option<String>,
+    /// ISO 8601, empty if unknown
+    pub updated_at:		String,
+}
+
+/// The `User-Agent` and the credentials in the `name` header
+fn credential_headers(name: HeaderName, credentials: &str) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    let mut value = HeaderValue::from_str(credentials)?;
+    value.set_sensitive(true);
+    headers.insert(name, value);
+    headers.insert(USER_AGENT, HeaderValue::from_static(concat!("priv-tauri-updater/", env!("CARGO_PKG_VERSION"))));
+    Ok( headers )
+}
@@ -0,0 +1,134 @@
+//! The update server as a standalone daemon, e.g. a `systemd` service next to the Tauri app (requires the `cli` feature)
+//!
+//! ```text
+//! priv-tauri-updater --account MyAccount --repo MyRepo --token MyGitHubToken [--port 7748] [--log-level info]
+//! priv-tauri-updater --account MyAccount --repo MyRepo --token MyGitHubToken --check 1.2.0
+//! ```
+//!
+//! The token can be passed as `PRIV_UPDATER_GH_TOKEN` instead, so that it doesn't show in the process list.
+//! The server runs until a `SIGTERM` or `SIGINT`. With `--check VERSION` nothing is served: the exit code
+//! is `0` if VERSION is the latest release, `1` if an update is available and `2` on errors.
+
+use priv_tauri_updater::PrivUpdaterBuilder;
+use std::{ error::Error, process::ExitCode };
+use tracing_subscriber::{ filter::Targets, layer::SubscriberExt, util::SubscriberInitExt };
+
+const USAGE: &str = "usage: priv-tauri-updater --account ACCOUNT --repo REPO [--token TOKEN] [--port PORT] [--log-level LEVEL] [--check VERSION]";
+
+struct Args {
+    account:	String,
+    repo:	String,
+    token:	String,
+    port:	Option<u16>,
+    log_level:	tracing::Level,
+    /// the version the app runs, see `--check`
+    check:	Option<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+	let (mut account, mut repo, mut token, mut port, mut log_level, mut check) = (None, None, None, None, tracing::Level::INFO, None);
+	while let Some(arg) = args.next() {
+	    let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+	    match arg.as_str() {
+		"--account"	=> account = Some(value()?),
+		"--repo"	=> repo = Some(value()?),
+		"--token"	=> token = Some(value()?),
+		"--port"	=> port = Some(value()?.parse().map_err(|_| String::from("--port needs a port number"))?),
+		"--log-level"	=> log_level = value()?.parse().map_err(|_| String::from("--log-level needs one of error, warn, info, debug and trace"))?,
+		"--check"	=> check = Some(value()?),
+		"--help" | "-h"	=> return Err(String::new()),
+		_		=> return Err(format!("unknown argument {arg}")),
+	    }
+	}
+	Ok( Self {
+	    account:	account.ok_or("--account is required")?,
+	    repo:	repo.ok_or("--repo is required")?,
+	    token:	token.or_else(|| std::env::var("PRIV_UPDATER_GH_TOKEN").ok()).ok_or("--token or PRIV_UPDATER_GH_TOKEN is required")?,
+	    port,
+	    log_level,
+	    check,
+	} )
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+	Ok(args)	=> args,
+	Err(message)	=> {
+	    if !message.is_empty() {
+		eprintln!("{message}");
+	    }
+	    eprintln!("{USAGE}");
+	    return ExitCode::from(2);
+	},
+    };
+    let _ = tracing_subscriber::registry()
+	.with(tracing_subscriber::fmt::layer())
+	.with(Targets::new().with_target("priv_tauri_updater", args.log_level))
+	.try_init();
+    match run(args).await {
+	Ok(code)	=> code,
+	Err(e)		=> {
+	    eprintln!("priv-tauri-updater: {e}");
+	    ExitCode::from(2)
+	},
+    }
+}
+
+async fn run(args: Args) -> Result<ExitCode, Box<dyn Error>> {
+    let mut builder = PrivUpdaterBuilder::new(args.account, args.repo, args.token);
+    if let Some(port) = args.port {
+	builder = builder.port(port);
+    }
+    if let Some(current_version) = args.check {
+	let latest = builder.build().await?.release_tag();
+	return Ok( if is_newer(&latest, &current_version) {
+	    println!("update available: {latest}");
+	    ExitCode::from(1)
+	} else {
+	    println!("up to date: {latest}");
+	    ExitCode::SUCCESS
+	} );
+    }
+
+    let handle = builder.serve().await?;
+    shutdown_signal().await?;
+    tracing::info!("shutting down");
+    handle.shutdown();
+    handle.await;
+    Ok( ExitCode::SUCCESS )
+}
+
+/// Whether the release tag `latest` (e.g. `v1.3.0`) is a newer version than `current`
+///
+/// The versions are compared by their numeric components, a tag that isn't a version is newer if it differs.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let components = |version: &str| version
+	.trim_start_matches('v')
+	.split(['.', '-', '+'])
+	.take(3)
+	.map(str::parse::<u64>)
+	.collect::<Result<Vec<_>, _>>();
+    match (components(latest), components(current)) {
+	(Ok(latest), Ok(current))	=> latest > current,
+	_				=> latest.trim_start_matches('v') != current.trim_start_matches('v'),
+    }
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{ SignalKind, signal };
+    let (mut terminate, mut interrupt) = (signal(SignalKind::terminate())?, signal(SignalKind::interrupt())?);
+    tokio::select! {
+	_ = terminate.recv()	=> {},
+	_ = interrupt.recv()	=> {},
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
@@ -0,0 +1,1105 @@
+//! Builder for a [`PrivUpdater`] with non-default settings
+
+use super::*;
+use backends::{ Backend, GITHUB_API, GitHubBackend };
+use reqwest::header::{ CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, LAST_MODIFIED };
+use std::{
+    path::{ Path, PathBuf },
+    time::Duration,
+};
+
+/// Configures and constructs a [`PrivUpdater`]
+///
+/// [`PrivUpdater::new()`] is a shorthand for `PrivUpdaterBuilder::new(..).build()` with the default settings.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use priv_tauri_updater::PrivUpdaterBuilder;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let updater = PrivUpdaterBuilder::new("MyGitHubAccount", "MyGitHubRepo", "MyGitHubToken")
+///     .server_addr(([127, 0, 0, 1], 8080))
+///     .warp_log(true, "update_server")
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PrivUpdaterBuilder {
+    gh_account_name:	String,
+    gh_repo_name:	String,
+    gh_token:		String,
+    github_api_base:	String,
+    backend:		Option<Arc<dyn Backend>>,
+    server_addr:	SocketAddr,
+    port_retry_delay:	Duration,
+    serve_timeout:	Duration,
+    health_check_timeout:	Duration,
+    warp_log:		Option<String>,
+    warp_log_format:	LogFormat,
+    access_log:		Option<PathBuf>,
+    access_log_format:	AccessLogFormat,
+    api_connect_timeout:	Option<Duration>,
+    max_assets:		Option<usize>,
+    use_browser_download_url:	bool,
+    download_url_override:	Option<String>,
+    legacy_accept_header:	bool,
+    preflight_check:	bool,
+    api_response_max_bytes:	u64,
+    checksum:		Option<ChecksumAlgorithm>,
+    user_agent:		Option<String>,
+    tcp_nodelay:	bool,
+    server_header:	Option<String>,
+    hide_server_header:	bool,
+    keep_alive_timeout:	Option<Duration>,
+    keep_alive_max_requests:	Option<usize>,
+    release_check_interval:	Option<Duration>,
+    max_upstream_fetches:	usize,
+    github_rate_limit:	Option<u32>,
+    max_connections:	Option<usize>,
+    shutdown_hooks:	ShutdownHooks,
+    shutdown_mode:	ShutdownMode,
+    root_certificates:	Vec<Vec<u8>>,
+    accept_invalid_certs:	bool,
+    max_redirects:	Option<usize>,
+    configure_client:	Option<Box<dyn Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send>>,
+    compression:	Compression,
+    #[cfg(feature = "brotli")]
+    brotli_quality:	u32,
+    configure_hyper:	Option<ConfigureHyper>,
+    cache_manifest:	bool,
+    manifest_cache_strict:	bool,
+    msix_mode:		bool,
+    asset_idle_timeout:	Duration,
+    response_send_timeout:	Option<Duration>,
+    stream_reconnect:	Option<u8>,
+    public_keys:	PublicKeys,
+    binary_cache_control:	String,
+    manifest_cache_control:	String,
+    forwarded_headers:	Vec<HeaderName>,
+    path_prefixes:	Vec<String>,
+    route_prefix:	String,
+    asset_paths:	Option<Vec<String>>,
+    #[cfg(target_os = "linux")]
+    reuse_port:		bool,
+    #[cfg(feature = "mtls")]
+    mtls:		Option<(Vec<u8>, Vec<u8>, Vec<u8>)>,
+    #[cfg(feature = "cache")]
+    response_cache:	Option<(usize, Duration)>,
+    #[cfg(feature = "cache")]
+    response_cache_max_file_size:	usize,
+    /// TTL of the disk cache
+    #[cfg(feature = "cache")]
+    disk_cache:		Option<Duration>,
+    #[cfg(feature = "cache")]
+    cache_dir:		Option<PathBuf>,
+    #[cfg(feature = "debug-headers")]
+    debug_request_headers:	bool,
+    #[cfg(feature = "middleware")]
+    middleware:		Vec<Arc<dyn reqwest_middleware::Middleware>>,
+    #[cfg(feature = "request-id")]
+    request_id_generator:	request_id::RequestIdGenerator,
+}
+
+impl PrivUpdaterBuilder {
+    /// Starts a builder with the default settings
+    ///
+    /// `gh_account_name` is case-insensitive
+    pub fn new<D: std::fmt::Display>(gh_account_name: D, gh_repo_name: D, gh_token: D) -> Self {
+	Self {
+	    gh_account_name:	gh_account_name.to_string(),
+	    gh_repo_name:	gh_repo_name.to_string(),
+	    gh_token:		gh_token.to_string(),
+	    github_api_base:	GITHUB_API.to_string(),
+	    backend:		None,
+	    server_addr:	SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 7748),
+	    port_retry_delay:	Duration::ZERO,
+	    serve_timeout:	Duration::from_secs(5),
+	    health_check_timeout:	Duration::from_secs(5),
+	    warp_log:		None,
+	    warp_log_format:	LogFormat::Common,
+	    access_log:		None,
+	    access_log_format:	AccessLogFormat::JsonLines,
+	    api_connect_timeout:	None,
+	    max_assets:		None,
+	    use_browser_download_url:	false,
+	    download_url_override:	None,
+	    legacy_accept_header:	false,
+	    preflight_check:	true,
+	    api_response_max_bytes:	release::DEFAULT_MAX_RESPONSE_BYTES,
+	    checksum:		None,
+	    user_agent:		None,
+	    tcp_nodelay:	true,
+	    server_header:	None,
+	    hide_server_header:	false,
+	    keep_alive_timeout:	None,
+	    keep_alive_max_requests:	None,
+	    release_check_interval:	None,
+	    max_upstream_fetches:	4,
+	    github_rate_limit:	None,
+	    max_connections:	None,
+	    shutdown_hooks:	ShutdownHooks::default(),
+	    shutdown_mode:	ShutdownMode::default(),
+	    root_certificates:	Vec::new(),
+	    accept_invalid_certs:	false,
+	    max_redirects:	Some(10),
+	    configure_client:	None,
+	    compression:	Compression::None,
+	    #[cfg(feature = "brotli")]
+	    brotli_quality:	11,
+	    configure_hyper:	None,
+	    cache_manifest:	false,
+	    manifest_cache_strict:	false,
+	    msix_mode:		false,
+	    asset_idle_timeout:	Duration::from_secs(120),
+	    response_send_timeout:	None,
+	    stream_reconnect:	None,
+	    public_keys:	PublicKeys::default(),
+	    binary_cache_control:	String::from("max-age=3600, immutable"),
+	    manifest_cache_control:	String::from("no-store"),
+	    forwarded_headers:	vec![CONTENT_TYPE, CONTENT_LENGTH, LAST_MODIFIED, ETAG, CACHE_CONTROL],
+	    path_prefixes:	Vec::new(),
+	    route_prefix:	String::new(),
+	    asset_paths:	None,
+	    #[cfg(target_os = "linux")]
+	    reuse_port:		false,
+	    #[cfg(feature = "mtls")]
+	    mtls:		None,
+	    #[cfg(feature = "cache")]
+	    response_cache:	None,
+	    #[cfg(feature = "cache")]
+	    response_cache_max_file_size:	0,
+	    #[cfg(feature = "cache")]
+	    disk_cache:		None,
+	    #[cfg(feature = "cache")]
+	    cache_dir:		None,
+	    #[cfg(feature = "debug-headers")]
+	    debug_request_headers:	false,
+	    #[cfg(feature = "middleware")]
+	    middleware:		Vec::new(),
+	    #[cfg(feature = "request-id")]
+	    request_id_generator:	Arc::new(request_id::uuid_v4),
+	}
+    }
+    /// Starts a builder from the environment variables, for deployments configured without code changes
+    ///
+    /// | Variable                              | Setting                                           |
+    /// |---------------------------------------|---------------------------------------------------|
+    /// | `PRIV_UPDATER_GH_ACCOUNT`             | `gh_account_name` of [`new`](Self::new), required |
+    /// | `PRIV_UPDATER_GH_REPO`                | `gh_repo_name` of [`new`](Self::new), required    |
+    /// | `PRIV_UPDATER_GH_TOKEN`               | `gh_token` of [`new`](Self::new), required        |
+    /// | `PRIV_UPDATER_SHUTDOWN_DRAIN_SECONDS` | [`ShutdownMode::Drain`] for that many seconds     |
+    /// | `PRIV_UPDATER_MAX_CONNECTIONS`        | [`max_connections`](Self::max_connections)        |
+    ///
+    /// The unset optional variables keep the defaults, the other settings can still be changed on the returned builder.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with a [`PrivUpdaterError::MissingEnvVar`] if a required variable isn't set, and with
+    /// a [`PrivUpdaterError::InvalidEnvVar`] naming the variable if a value can't be parsed.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+	Ok( Self::from_vars(|name| std::env::var(name).ok())? )
+    }
+    /// [`from_env`](Self::from_env) with the variables looked up through `var`
+    pub(crate) fn from_vars(var: impl Fn(&'static str) -> Option<String>) -> Result<Self, PrivUpdaterError> {
+	let required = |name| var(name).ok_or(PrivUpdaterError::MissingEnvVar(name));
+	let number = |name, expected, min| var(name)
+	    .map(|value| match value.trim().parse::<u64>() {
+		Ok(n) if n >= min	=> Ok(n),
+		_			=> Err(PrivUpdaterError::InvalidEnvVar { name, expected, value }),
+	    })
+	    .transpose();
+
+	let mut builder = Self::new(required("PRIV_UPDATER_GH_ACCOUNT")?, required("PRIV_UPDATER_GH_REPO")?, required("PRIV_UPDATER_GH_TOKEN")?);
+	if let Some(seconds) = number("PRIV_UPDATER_SHUTDOWN_DRAIN_SECONDS", "a non-negative integer", 0)? {
+	    builder = builder.shutdown_mode(ShutdownMode::Drain(Duration::from_secs(seconds)));
+	}
+	if let Some(n) = number("PRIV_UPDATER_MAX_CONNECTIONS", "a positive integer", 1)? {
+	    builder = builder.max_connections(usize::try_from(n).unwrap_or(usize::MAX));
+	}
+	Ok( builder )
+    }
+    /// Root of the GitHub REST API the release is fetched from (default: `https://api.github.com`)
+    ///
+    /// For a GitHub Enterprise Server it's `https://{hostname}/api/v3`.
+    pub fn github_api_base(mut self, url: &str) -> Self {
+	self.github_api_base = url.trim_end_matches('/').to_string();
+	self
+    }
+    /// Fetches the release from `backend` instead of the GitHub repository of [`new`](Self::new), e.g. a [`GitLabBackend`](backends::GitLabBackend)
+    ///
+    /// The GitHub specific settings ([`github_api_base`](Self::github_api_base), [`user_agent`](Self::user_agent),
+    /// [`use_legacy_accept_header`](Self::use_legacy_accept_header) and [`preflight_check`](Self::preflight_check))
+    /// don't apply to it, the backend sets its own headers.
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+	self.backend = Some(Arc::new(backend));
+	self
+    }
+    /// Address the update server binds to (default: `127.0.0.1:7748`)
+    pub fn server_addr<S: Into<SocketAddr>>(mut self, server_addr: S) -> Self {
+	self.server_addr = server_addr.into();
+	self
+    }
+    /// Port the update server binds to, on the address of [`server_addr`](Self::server_addr) (default: `7748`)
+    pub fn port(mut self, port: u16) -> Self {
+	self.server_addr.set_port(port);
+	self
+    }
+    /// Waits `delay` before trying the next port when the server can't bind its port (default: no delay)
+    ///
+    /// Rarely needed: binding fails right away when the port is taken. It helps with transient failures on systems
+    /// with a high port churn (e.g. test environments running many networked processes), `10ms` is enough.
+    pub fn port_retry_delay(mut self, delay: Duration) -> Self {
+	self.port_retry_delay = delay;
+	self
+    }
+    /// How long [`PrivUpdater::serve_update`] may try the ports before giving up (default: 5 seconds)
+    ///
+    /// Covers all the retries together, after which it fails with [`PrivUpdaterError::ServerStartTimeout`].
+    /// The requests to GitHub made by [`build()`](Self::build) don't count.
+    pub fn serve_timeout(mut self, timeout: Duration) -> Self {
+	self.serve_timeout = timeout;
+	self
+    }
+    /// How long each probe of [`UpdateServerHandle::full_health_check`] may take before it counts as failed (default: 5 seconds)
+    pub fn health_check_timeout(mut self, timeout: Duration) -> Self {
+	self.health_check_timeout = timeout;
+	self
+    }
+    /// Logs every request made to the update server (default: disabled)
+    ///
+    /// Each request is logged at `INFO` level in the [`warp_log_format`](Self::warp_log_format) under the log
+    /// `target`, so it can be filtered with `RUST_LOG={target}=info`.
+    /// With the `tracing` feature enabled the requests are emitted as `tracing` events instead of `log` records.
+    /// Their target is `priv_tauri_updater::access_log` because `tracing` targets are static, and the given
+    /// `target` is recorded in their `log_target` field, so filter on that field instead.
+    pub fn warp_log(mut self, enabled: bool, target: &str) -> Self {
+	self.warp_log = enabled.then(|| target.to_string());
+	self
+    }
+    /// Format of the requests logged by [`warp_log`](Self::warp_log) (default: [`LogFormat::Common`])
+    ///
+    /// The Common and Combined Log Formats of Apache are understood by the usual log analysis tools.
+    pub fn warp_log_format(mut self, format: LogFormat) -> Self {
+	self.warp_log_format = format;
+	self
+    }
+    /// Appends every request made to the update server to the file at `path`, an audit log (default: disabled)
+    ///
+    /// The file is created if absent, [`build()`](Self::build) fails if it can't be opened. Independent of the
+    /// [`warp_log`](Self::warp_log), both can be enabled.
+    pub fn access_log(mut self, path: PathBuf) -> Self {
+	self.access_log = Some(path);
+	self
+    }
+    /// Format of the [`access_log`](Self::access_log) file (default: [`AccessLogFormat::JsonLines`])
+    pub fn access_log_format(mut self, format: AccessLogFormat) -> Self {
+	self.access_log_format = format;
+	self
+    }
+    /// Connect timeout for the GitHub API calls fetching the release info (default: none)
+    ///
+    /// Applies to the client used during [`build()`](Self::build) and by [`PrivUpdater::reload_assets`];
+    /// the client proxying the assets is configured separately.
+    pub fn api_connect_timeout(mut self, duration: Duration) -> Self {
+	self.api_connect_timeout = Some(duration);
+	self
+    }
+    /// Maximum number of release assets kept in memory (default: unlimited)
+    ///
+    /// When the release has more assets, they are sorted by name and only the first `n` are served.
+    /// With the `tracing` feature enabled a warning is emitted when this happens.
+    pub fn max_assets(mut self, n: usize) -> Self {
+	self.max_assets = Some(n);
+	self
+    }
+    /// Checks for a new release every `interval` while the server runs (default: disabled)
+    ///
+    /// Only asks GitHub whether the release info changed since the last check (with its `ETag`), which is cheap and
+    /// doesn't count against the rate limit when it didn't. A new release is announced right away through
+    /// [`PrivUpdater::new_release_receiver`], but only served from the next request on, e.g. once the Tauri app
+    /// checks for the update it was notified of.
+    pub fn release_check_interval(mut self, interval: Duration) -> Self {
+	self.release_check_interval = Some(interval);
+	self
+    }
+    /// Download the assets from their `browser_download_url` instead of the API `url` (default: `false`)
+    ///
+    /// This skips the redirect through GitHub's API. The `Authorization` header is still sent
+    /// (GitHub ignores it on the CDN URLs).
+    pub fn use_browser_download_url(mut self, enabled: bool) -> Self {
+	self.use_browser_download_url = enabled;
+	self
+    }
+    /// Replaces the URLs starting with `base_url` in `latest.json` by the server address (default: derived from the assets)
+    ///
+    /// By default it's the directory of the assets' `browser_download_url`, e.g.
+    /// `https://github.com/{account}/{repo}/releases/download/{tag}`. Some GitHub Enterprise Servers redirect the
+    /// downloads through a CDN and the manifest uses the CDN URLs instead, which then aren't rewritten without this.
+    pub fn download_url_override(mut self, base_url: &str) -> Self {
+	self.download_url_override = Some(base_url.to_string());
+	self
+    }
+    /// Requests the release info with `Accept: application/vnd.github.v3+json` (default: `false`)
+    ///
+    /// For older GitHub Enterprise Servers that don't support the `X-GitHub-Api-Version` header (which is still sent)
+    /// and expect the version in the media type instead.
+    #[deprecated(note = "the v3 media type is legacy, only use it for GitHub Enterprise Servers without `X-GitHub-Api-Version` support")]
+    pub fn use_legacy_accept_header(mut self, enabled: bool) -> Self {
+	self.legacy_accept_header = enabled;
+	self
+    }
+    /// Checks that the GitHub API is reachable before fetching the release info (default: `true`)
+    ///
+    /// Makes [`build()`](Self::build) fail with [`PrivUpdaterError::GitHubUnreachable`] when GitHub can't be reached
+    /// or is down, instead of an error that could also mean a wrong repo or token. Costs one unauthenticated request.
+    pub fn preflight_check(mut self, enabled: bool) -> Self {
+	self.preflight_check = enabled;
+	self
+    }
+    /// Maximum size of a release info response of the GitHub API (default: 10 MiB)
+    ///
+    /// A bigger response fails with [`PrivUpdaterError::ApiResponseTooLarge`] before it's buffered in full, so that
+    /// a broken or malicious server can't exhaust the memory. The release info of a few hundred assets is well below it.
+    pub fn api_response_max_bytes(mut self, limit: u64) -> Self {
+	self.api_response_max_bytes = limit;
+	self
+    }
+    /// Verify the downloaded assets against the checksum files of the release before serving them (default: disabled)
+    ///
+    /// An asset that fails verification is not served. `latest.json` and the checksum files themselves aren't verified.
+    pub fn verify_checksums(mut self, algorithm: ChecksumAlgorithm) -> Self {
+	self.checksum = Some(algorithm);
+	self
+    }
+    /// Replaces the default `User-Agent` sent to GitHub (`priv-tauri-updater/{crate_version} ({repo_name}/{account_name})`)
+    pub fn user_agent(mut self, ua: &str) -> Self {
+	self.user_agent = Some(ua.to_string());
+	self
+    }
+    /// Replaces the default `Server` header of the update server's responses (`priv-tauri-updater/{crate_version}`)
+    ///
+    /// [`build()`](Self::build) fails if `value` isn't a valid header value.
+    pub fn server_header(mut self, value: &str) -> Self {
+	self.server_header = Some(value.to_string());
+	self
+    }
+    /// Leaves out the `Server` header of the update server's responses (default: `false`)
+    pub fn hide_server_header(mut self, hidden: bool) -> Self {
+	self.hide_server_header = hidden;
+	self
+    }
+    /// Sets `TCP_NODELAY` on the connections accepted by the update server (default: `true`)
+    ///
+    /// Leave this enabled for the typical update proxy: with Nagle's algorithm the small `latest.json`
+    /// response can be delayed by up to 200ms, making Tauri's update check feel sluggish.
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+	self.tcp_nodelay = enabled;
+	self
+    }
+    /// Closes the connections that stay idle for `duration`, without a request being served (default: never)
+    ///
+    /// Keep-alive lets the Tauri updater download the asset on the connection of its `latest.json` request, but the
+    /// connections it leaves open otherwise stay until the client closes them. A response taking longer doesn't count as idle.
+    pub fn keep_alive_timeout(mut self, duration: Duration) -> Self {
+	self.keep_alive_timeout = Some(duration);
+	self
+    }
+    /// Closes the HTTP/1 connections after `n` requests, answering the last one with `Connection: close` (default: unlimited)
+    pub fn keep_alive_max_requests(mut self, n: usize) -> Self {
+	self.keep_alive_max_requests = Some(n.max(1));
+	self
+    }
+    /// Sets `SO_REUSEPORT` on the socket of the update server (default: `false`, Linux only)
+    ///
+    /// Lets several processes bind the same `server_addr`, e.g. when the Tauri app restarts before the previous
+    /// instance exited, instead of retrying with the next port. The kernel then load-balances the incoming
+    /// connections between all the processes bound to the port: until the old server shuts down, some
+    /// requests are handled by it (and its release). Not available on macOS, where `SO_REUSEPORT` doesn't balance
+    /// connections but hands them all to the last bound socket.
+    #[cfg(target_os = "linux")]
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+	self.reuse_port = enabled;
+	self
+    }
+    /// Serves over HTTPS, and only to clients presenting a certificate signed by a CA of `client_ca_pem` (requires the `mtls` feature)
+    ///
+    /// `cert_chain_pem` and `key_pem` are the certificate and private key of the update server, which the Tauri app
+    /// must trust, and the app needs a client certificate and key of its own. More client CAs can be trusted while
+    /// the server runs with [`UpdateServerHandle::add_client_cert`]. The PEMs are parsed in [`build()`](Self::build),
+    /// which fails if they're invalid or if [`configure_hyper`](Self::configure_hyper) is used too.
+    #[cfg(feature = "mtls")]
+    pub fn mtls(mut self, cert_chain_pem: &[u8], key_pem: &[u8], client_ca_pem: &[u8]) -> Self {
+	self.mtls = Some((cert_chain_pem.to_vec(), key_pem.to_vec(), client_ca_pem.to_vec()));
+	self
+    }
+    /// Maximum number of simultaneous requests to GitHub while proxying (default: `4`, at least `1`)
+    ///
+    /// Further requests wait until one of the running fetches completes, so that several Tauri windows
+    /// updating at once don't get the proxy rate-limited.
+    pub fn max_concurrent_upstream_fetches(mut self, n: usize) -> Self {
+	self.max_upstream_fetches = n.max(1);
+	self
+    }
+    /// Maximum number of requests to GitHub per minute made by the update server, in bursts of at most as many (default: unlimited)
+    ///
+    /// For many Tauri instances checking for updates at once, which could otherwise exhaust GitHub's hourly rate limit.
+    /// The requests served from a cache don't count, the ones beyond the limit are answered with a
+    /// `429 Too Many Requests` whose `Retry-After` says when to try again.
+    pub fn github_rate_limit(mut self, requests_per_minute: u32) -> Self {
+	self.github_rate_limit = Some(requests_per_minute.max(1));
+	self
+    }
+    /// Maximum number of requests the update server answers simultaneously (default: unlimited, at least `1`)
+    ///
+    /// The requests beyond it are answered right away with a `503 Service Unavailable` instead of queuing up, with a
+    /// `Retry-After: 1` and the JSON body `{"error": "too_many_connections", "limit": n}`.
+    /// See [`UpdateServerHandle::active_connections`](crate::UpdateServerHandle::active_connections).
+    pub fn max_connections(mut self, n: usize) -> Self {
+	self.max_connections = Some(n.max(1));
+	self
+    }
+    /// Runs `f` when the update server shuts down, before it stops accepting connections
+    ///
+    /// The hook is called synchronously (blocking the shutdown), so it should be fast.
+    /// It's also called if the server future is cancelled.
+    pub fn on_shutdown(mut self, f: impl FnOnce() + Send + 'static) -> Self {
+	self.shutdown_hooks.sync_hook = Some(Box::new(f));
+	self
+    }
+    /// Awaits `f` when the update server shuts down, for at most the [`shutdown_hook_timeout`](Self::shutdown_hook_timeout)
+    ///
+    /// Runs after the [`on_shutdown`](Self::on_shutdown) hook. If the server future is cancelled,
+    /// `f` is spawned onto the current tokio runtime instead.
+    pub fn on_shutdown_async(mut self, f: impl Future<Output = ()> + Send + 'static) -> Self {
+	self.shutdown_hooks.async_hook = Some(Box::pin(f));
+	self
+    }
+    /// How long the [`on_shutdown_async`](Self::on_shutdown_async) hook may take before the server stops anyway (default: 5 seconds)
+    pub fn shutdown_hook_timeout(mut self, duration: Duration) -> Self {
+	self.shutdown_hooks.async_timeout = duration;
+	self
+    }
+    /// Writes the URL of the update server (e.g. `http://127.0.0.1:7748`, followed by the [`route_prefix`](Self::route_prefix))
+    /// to `path` once it's serving (default: disabled)
+    ///
+    /// For a Tauri app running in another process than the update server, which can then find the (possibly retried)
+    /// port with [`read_server_url`](crate::read_server_url). The file is replaced atomically and removed when the
+    /// server shuts down.
+    pub fn publish_url_to_file(mut self, path: PathBuf) -> Self {
+	self.shutdown_hooks.url_file = Some(path);
+	self
+    }
+    /// What happens to the open connections when the update server shuts down (default: [`ShutdownMode::WaitForCompletion`])
+    ///
+    /// The server stops accepting connections and closes the idle ones right away in every mode. Waiting for the
+    /// responses in flight can take long with big assets on a slow connection, hence [`ShutdownMode::Drain`].
+    pub fn shutdown_mode(mut self, mode: ShutdownMode) -> Self {
+	self.shutdown_mode = mode;
+	self
+    }
+    /// Trusts an additional root certificate (PEM encoded) for the connections to GitHub, can be called multiple times
+    ///
+    /// Needed for GitHub Enterprise Servers using a corporate CA which isn't in the system trust store.
+    /// Only add CAs you control: any certificate they sign is trusted to receive the GitHub token.
+    /// The PEM is parsed in [`build()`](Self::build), which fails if it's invalid.
+    pub fn add_root_certificate(mut self, pem: &[u8]) -> Self {
+	self.root_certificates.push(pem.to_vec());
+	self
+    }
+    /// Reads a PEM encoded root certificate from `path`, see [`add_root_certificate`](Self::add_root_certificate)
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the file can't be read.
+    pub fn add_root_certificate_file(self, path: &Path) -> Result<Self, std::io::Error> {
+	let pem = std::fs::read(path)?;
+	Ok( self.add_root_certificate(&pem) )
+    }
+    /// Disables the TLS certificate validation of the connections to GitHub (default: `false`)
+    ///
+    /// **This is insecure**: anyone able to intercept the connection receives the GitHub token and can serve
+    /// arbitrary updates. Only use it in emergencies, [`add_root_certificate`](Self::add_root_certificate)
+    /// is the way to trust a self-hosted GitHub Enterprise Server.
+    #[deprecated(note = "accepting invalid certificates is insecure, only use it in emergencies")]
+    pub fn accept_invalid_certs(mut self, accept: bool) -> Self {
+	self.accept_invalid_certs = accept;
+	self
+    }
+    /// Maximum number of redirects followed by the requests to GitHub (default: `10`)
+    ///
+    /// The asset downloads through the API `url` are redirected once to the CDN, so `n` should be at least `1`
+    /// unless [`use_browser_download_url`](Self::use_browser_download_url) is enabled.
+    pub fn max_redirects(mut self, n: usize) -> Self {
+	self.max_redirects = Some(n);
+	self
+    }
+    /// Doesn't follow any redirect of GitHub, the redirect responses are passed through instead
+    ///
+    /// For callers handling the redirects themselves, typically with [`use_browser_download_url`](Self::use_browser_download_url).
+    /// [`max_redirects`](Self::max_redirects) enables them again.
+    pub fn disable_redirects(mut self) -> Self {
+	self.max_redirects = None;
+	self
+    }
+    /// Applies `f` to the builders of the clients making the requests to GitHub, just before they're built
+    ///
+    /// Escape hatch for the `reqwest` options this builder doesn't expose (e.g. `tcp_keepalive`). `f` is called
+    /// for the client fetching the release info and for the one proxying the assets, after the mandatory
+    /// headers and the other settings of this builder are applied, so it can override them.
+    pub fn configure_client(mut self, f: impl Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + 'static) -> Self {
+	self.configure_client = Some(Box::new(f));
+	self
+    }
+    /// Compresses the `latest.json` responses for the clients accepting the `compression` (default: [`Compression::None`])
+    ///
+    /// The binary assets are always served as they are. Clients whose `Accept-Encoding` doesn't list the
+    /// encoding get the uncompressed manifest.
+    pub fn compress_manifest(mut self, compression: Compression) -> Self {
+	self.compression = compression;
+	self
+    }
+    /// Quality of the [`Compression::Brotli`] manifest, from `0` (fastest) to `11` (smallest, default)
+    #[cfg(feature = "brotli")]
+    pub fn brotli_quality(mut self, quality: u32) -> Self {
+	self.brotli_quality = quality.min(11);
+	self
+    }
+    /// Applies `f` to the `hyper` server builder of the update server, just before it starts serving
+    ///
+    /// Advanced escape hatch for the connection settings this builder doesn't expose (e.g. `http1_keepalive`
+    /// or `http2_only`). **Stability caveat**: the types are those of the `hyper` version `warp` depends on
+    /// (re-exported as `warp::hyper`), so this changes whenever `warp` upgrades `hyper`, even in a minor release
+    /// of this crate. Incompatible settings (e.g. HTTP/2 only, while Tauri speaks HTTP/1.1) break the updater.
+    /// Not available with [`mtls`](Self::mtls), whose server accepts TLS streams rather than an `AddrIncoming`.
+    pub fn configure_hyper(mut self, f: impl FnOnce(hyper::server::Builder<AddrIncoming>) -> hyper::server::Builder<AddrIncoming> + Send + 'static) -> Self {
+	self.configure_hyper = Some(Box::new(f));
+	self
+    }
+    /// Fetches and rewrites `latest.json` once, then serves it without calling GitHub (default: `false`)
+    ///
+    /// The manifest is fetched during [`build()`](Self::build), which fails if it can't be, and only fetched again by
+    /// [`PrivUpdater::reload_assets`]. Meant for manifests generated in CI and uploaded with the release.
+    /// This is the fastest option for the manifest endpoint, the [`response_cache`](Self::response_cache)
+    /// expires instead.
+    pub fn cache_manifest(mut self, enabled: bool) -> Self {
+	self.cache_manifest = enabled;
+	self
+    }
+    /// Answers `latest.json` with a `503 Service Unavailable` while the [`cache_manifest`](Self::cache_manifest) is empty (default: `false`)
+    ///
+    /// The cache is empty when the release has no `latest.json`. Otherwise the manifest is fetched from GitHub
+    /// and cached on the first request.
+    pub fn serve_manifest_only_from_cache(mut self, strict: bool) -> Self {
+	self.manifest_cache_strict = strict;
+	self
+    }
+    /// Serves the `.msix` and `.appx` assets with `Content-Type: application/x-msix` and `Content-Disposition: attachment` (default: `false`)
+    ///
+    /// Workaround for MSIX/APPX installer downloads stalling in some WebView2 versions when these headers are
+    /// missing. The affected Tauri and WebView2 versions haven't been narrowed down, so only enable this if
+    /// the downloads of these bundles actually stall.
+    pub fn msix_mode(mut self, enabled: bool) -> Self {
+	self.msix_mode = enabled;
+	self
+    }
+    /// Aborts an asset download when the Tauri app doesn't receive anything for `duration` (default: 120 seconds)
+    ///
+    /// A paused or stalled download otherwise keeps its connection (and the buffered asset) forever. The assets
+    /// are sent in 64 KiB chunks, a few of which hyper buffers before the client has to keep up.
+    pub fn asset_idle_timeout(mut self, duration: Duration) -> Self {
+	self.asset_idle_timeout = duration;
+	self
+    }
+    /// Drops the connection when a response isn't sent within `duration` (default: disabled)
+    ///
+    /// `latest.json` is small, so all of it has to be handed over within `duration`. The binary assets are streamed
+    /// instead: the timeout applies to every chunk, taking precedence over the [`asset_idle_timeout`](Self::asset_idle_timeout).
+    /// With the `tracing` feature, a warning names the asset whose response timed out.
+    pub fn response_send_timeout(mut self, duration: Duration) -> Self {
+	self.response_send_timeout = Some(duration);
+	self
+    }
+    /// Resumes the asset downloads from GitHub whose connection drops, up to `max_retries` times (default: disabled)
+    ///
+    /// The download continues with a `Range` request from the bytes received so far, after 250 ms doubled on
+    /// every retry up to 30 seconds. If the CDN doesn't support `Range` requests, the whole file is downloaded again. The assets
+    /// are buffered before being served, so the client only ever receives the complete file.
+    pub fn stream_reconnect(mut self, enabled: bool, max_retries: u8) -> Self {
+	self.stream_reconnect = enabled.then_some(max_retries);
+	self
+    }
+    /// Sets the top-level `pub_key` of the served `latest.json` to `key` (default: the manifest is left as is)
+    ///
+    /// For manifests generated by third-party tools that don't know the key. The manifest is re-serialized
+    /// when a key is injected, its objects then list their keys in sorted order.
+    pub fn inject_public_key(mut self, key: &str) -> Self {
+	self.public_keys.key = Some(key.to_string());
+	self
+    }
+    /// Appends `key` to the `pub_keys` array of the served `latest.json`, if it has one, e.g. while rotating keys
+    ///
+    /// Can be called multiple times, see [`inject_public_key`](Self::inject_public_key).
+    pub fn inject_additional_public_key(mut self, key: &str) -> Self {
+	self.public_keys.additional.push(key.to_string());
+	self
+    }
+    /// `Cache-Control` of the binary assets (default: `max-age=3600, immutable`)
+    ///
+    /// The assets of a release never change once uploaded, so the clients needn't revalidate them.
+    /// The directive is parsed in [`build()`](Self::build), which fails if it isn't a valid header value.
+    pub fn binary_cache_control(mut self, directive: &str) -> Self {
+	self.binary_cache_control = directive.to_string();
+	self
+    }
+    /// `Cache-Control` of `latest.json` (default: `no-store`), which must be fresh for the updates to be found
+    pub fn manifest_cache_control(mut self, directive: &str) -> Self {
+	self.manifest_cache_control = directive.to_string();
+	self
+    }
+    /// The headers of the upstream asset responses passed on to the client, the others are stripped
+    ///
+    /// Defaults to `content-type`, `content-length`, `last-modified`, `etag` and `cache-control`, which keeps
+    /// the internal headers of GitHub's CDN (`x-amz-request-id`, ...) from reaching the Tauri app. The
+    /// [`binary_cache_control`](Self::binary_cache_control) and [`msix_mode`](Self::msix_mode) headers take
+    /// precedence. `latest.json` is rewritten, so none of its upstream headers apply and it never gets any.
+    pub fn forwarded_response_headers(mut self, headers: Vec<HeaderName>) -> Self {
+	self.forwarded_headers = headers;
+	self
+    }
+    /// Serves the assets under `prefix` too, e.g. `/v1/latest.json` as `latest.json` with `prefix = "/v1"`
+    ///
+    /// For the Tauri update endpoints with a version in their path, without registering the assets again. Can be
+    /// called multiple times, the longest prefix matching a request is stripped. Requests under other prefixes stay
+    /// 404s, the assets are still served without a prefix.
+    pub fn path_prefix_strip(mut self, prefix: &str) -> Self {
+	let prefix = prefix.trim_matches('/');
+	if !prefix.is_empty() {
+	    self.path_prefixes.push(format!("/{prefix}"));
+	}
+	self
+    }
+    /// Serves the assets under `prefix` instead of the root, e.g. at `/api/v1/update/{filename}` with
+    /// `prefix = "/api/v1/update"` (default: none)
+    ///
+    /// Unlike with [`path_prefix_strip`](Self::path_prefix_strip), the assets are only served under it: the URLs of
+    /// the rewritten `latest.json`, the [`UpdateServerHandle::tauri_endpoint`] and the [published](Self::publish_url_to_file)
+    /// URL include it. `/health` and `/robots.txt` stay at the root.
+    pub fn route_prefix(mut self, prefix: &str) -> Self {
+	self.route_prefix = prefix.split('/').filter(|segment| !segment.is_empty()).map(|segment| format!("/{segment}")).collect();
+	self
+    }
+    /// Serves the assets at each of the path `patterns` only, e.g. `["{filename}", "windows/{filename}", "{platform}/{filename}"]`
+    /// (default: at `/{filename}`, ignoring any further segment)
+    ///
+    /// Every pattern ends with `{filename}`, the asset looked up, the other segments in braces match any segment.
+    /// The patterns apply after the [`route_prefix`](Self::route_prefix) and the [`path_prefix_strip`](Self::path_prefix_strip).
+    /// [`build()`](Self::build) fails with [`PrivUpdaterError::InvalidPathPattern`] if a pattern doesn't end with `{filename}`.
+    pub fn serve_at_paths(mut self, patterns: Vec<String>) -> Self {
+	self.asset_paths = Some(patterns);
+	self
+    }
+    /// Caches the `latest.json` responses in memory for `ttl`, keeping at most `capacity` entries (default: disabled)
+    ///
+    /// Cache hits are served without calling GitHub, the least recently used entries are evicted first.
+    /// See [`UpdateServerHandle::cache_stats`] for the hit rate.
+    #[cfg(feature = "cache")]
+    pub fn response_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+	self.response_cache = Some((capacity, ttl));
+	self
+    }
+    /// Also caches the assets up to `max_bytes` in size when the [`response_cache`](Self::response_cache) is enabled (default: `0`)
+    #[cfg(feature = "cache")]
+    pub fn response_cache_max_file_size(mut self, max_bytes: usize) -> Self {
+	self.response_cache_max_file_size = max_bytes;
+	self
+    }
+    /// Caches the assets on disk for `ttl`, so that a restarted server doesn't download them again (default: disabled)
+    ///
+    /// The assets are stored in the [`cache_dir`](Self::cache_dir), by release. `latest.json` is rewritten with the server
+    /// address and never stored, see the [`response_cache`](Self::response_cache) for it.
+    #[cfg(feature = "cache")]
+    pub fn cache(mut self, enabled: bool, ttl: Duration) -> Self {
+	self.disk_cache = enabled.then_some(ttl);
+	self
+    }
+    /// Where the [`cache`](Self::cache) stores the assets (default: `priv-tauri-updater/{account}-{repo}` in the temp directory)
+    ///
+    /// The directory is created if absent. [`PrivUpdater::clear_cache`] empties it, don't share it with other files.
+    #[cfg(feature = "cache")]
+    pub fn cache_dir(mut self, path: impl Into<PathBuf>) -> Self {
+	self.cache_dir = Some(path.into());
+	self
+    }
+    /// Logs the headers of every incoming request at `TRACE` level (default: `false`)
+    ///
+    /// Helps diagnosing which headers the Tauri updater actually sends. Credentials (`Authorization`, `Cookie`, ..)
+    /// are logged as `[REDACTED]`. Only available with the `debug-headers` feature, which shouldn't be enabled in production.
+    #[cfg(feature = "debug-headers")]
+    pub fn debug_request_headers(mut self, enabled: bool) -> Self {
+	self.debug_request_headers = enabled;
+	self
+    }
+    /// Sends the requests to GitHub through `mw`, e.g. for retries or OpenTelemetry tracing (default: none)
+    ///
+    /// Can be called multiple times, the middleware run in the order they were added.
+    /// It applies to the release info fetches as well as the proxied assets.
+    /// Only available with the `middleware` feature.
+    #[cfg(feature = "middleware")]
+    pub fn with_middleware(mut self, mw: Arc<dyn reqwest_middleware::Middleware>) -> Self {
+	self.middleware.push(mw);
+	self
+    }
+    /// Replaces the generator of the `X-Request-ID` sent with every response (default: a random UUID v4)
+    ///
+    /// `f` is called once per request, e.g. with [`nanoid_request_id`](crate::nanoid_request_id) (`nanoid` feature)
+    /// for shorter IDs, or with a counter for sequential ones. With the `tracing` feature the ID is also
+    /// recorded as the `request_id` field of the request's span. Only available with the `request-id` feature.
+    #[cfg(feature = "request-id")]
+    pub fn request_id_generator(mut self, f: impl Fn() -> String + Send + Sync + 'static) -> Self {
+	self.request_id_generator = Arc::new(f);
+	self
+    }
+    /// The serializable settings of this builder, see [`PrivUpdaterConfig`]
+    pub fn config(&self) -> PrivUpdaterConfig {
+	let millis = |duration: Duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+	PrivUpdaterConfig {
+	    gh_account_name:		self.gh_account_name.clone(),
+	    gh_repo_name:		self.gh_repo_name.clone(),
+	    github_api_base:		self.github_api_base.clone(),
+	    server_addr:		self.server_addr,
+	    port_retry_delay_ms:	millis(self.port_retry_delay),
+	    serve_timeout_ms:		millis(self.serve_timeout),
+	    health_check_timeout_ms:	millis(self.health_check_timeout),
+	    warp_log:			self.warp_log.clone(),
+	    warp_log_format:		self.warp_log_format.clone(),
+	    access_log:			self.access_log.clone(),
+	    access_log_format:		self.access_log_format,
+	    api_connect_timeout_ms:	self.api_connect_timeout.map(millis),
+	    max_assets:		self.max_assets,
+	    use_browser_download_url:	self.use_browser_download_url,
+	    download_url_override:	self.download_url_override.clone(),
+	    legacy_accept_header:	self.legacy_accept_header,
+	    preflight_check:	self.preflight_check,
+	    api_response_max_bytes:	self.api_response_max_bytes,
+	    checksum:			self.checksum,
+	    user_agent:		self.user_agent.clone(),
+	    tcp_nodelay:		self.tcp_nodelay,
+	    server_header:		self.server_header.clone(),
+	    hide_server_header:		self.hide_server_header,
+	    keep_alive_timeout_ms:	self.keep_alive_timeout.map(millis),
+	    keep_alive_max_requests:	self.keep_alive_max_requests,
+	    release_check_interval_ms:	self.release_check_interval.map(millis),
+	    max_upstream_fetches:	self.max_upstream_fetches,
+	    github_rate_limit:		self.github_rate_limit,
+	    max_redirects:		self.max_redirects,
+	    max_connections:		self.max_connections,
+	    shutdown_hook_timeout_ms:	millis(self.shutdown_hooks.async_timeout),
+	    shutdown_mode:		self.shutdown_mode,
+	    publish_url_to_file:	self.shutdown_hooks.url_file.clone(),
+	    compression:		self.compression,
+	    #[cfg(feature = "brotli")]
+	    brotli_quality:		self.brotli_quality,
+	    cache_manifest:		self.cache_manifest,
+	    manifest_cache_strict:	self.manifest_cache_strict,
+	    msix_mode:			self.msix_mode,
+	    asset_idle_timeout_ms:	millis(self.asset_idle_timeout),
+	    response_send_timeout_ms:	self.response_send_timeout.map(millis),
+	    stream_reconnect:		self.stream_reconnect,
+	    public_key:			self.public_keys.key.clone(),
+	    additional_public_keys:	self.public_keys.additional.clone(),
+	    binary_cache_control:	self.binary_cache_control.clone(),
+	    manifest_cache_control:	self.manifest_cache_control.clone(),
+	    forwarded_response_headers:	self.forwarded_headers.iter().map(|name| name.to_string()).collect(),
+	    path_prefixes:		self.path_prefixes.clone(),
+	    route_prefix:		self.route_prefix.clone(),
+	    serve_at_paths:		self.asset_paths.clone(),
+	    #[cfg(target_os = "linux")]
+	    reuse_port:		self.reuse_port,
+	    #[cfg(feature = "cache")]
+	    response_cache:		self.response_cache.map(|(capacity, ttl)| (capacity, millis(ttl))),
+	    #[cfg(feature = "cache")]
+	    response_cache_max_file_size:	self.response_cache_max_file_size,
+	    #[cfg(feature = "cache")]
+	    cache_ttl_ms:		self.disk_cache.map(millis),
+	    #[cfg(feature = "cache")]
+	    cache_dir:			self.cache_dir.clone(),
+	    #[cfg(feature = "debug-headers")]
+	    debug_request_headers:	self.debug_request_headers,
+	}
+    }
+    /// Fetches the latest release info from GitHub and constructs the [`PrivUpdater`]
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `gh_account_name`, `gh_repo_name`, or `gh_token` are incorrect for GitHub
+    /// or invalid as HeaderNames (see [reqwest docs](https://docs.rs/reqwest/latest/reqwest/header/struct.HeaderValue.html#method.from_str)).
+    /// A `user_agent` containing a newline fails with [`PrivUpdaterError::InvalidUserAgent`].
+    /// If GitHub refuses access (403) the error is a [`PrivUpdaterError::InsufficientPermissions`]
+    /// explaining which scope or permission the token is missing, unless the token's rate limit is exhausted:
+    /// that's a [`PrivUpdaterError::GitHubRateLimited`] telling when it resets.
+    /// If GitHub can't be reached at all it's a [`PrivUpdaterError::GitHubUnreachable`], see [`preflight_check`](Self::preflight_check).
+    pub async fn build(self) -> Result<PrivUpdater, Box<dyn Error>> {
+	let Self {
+	    gh_account_name,
+	    gh_repo_name,
+	    gh_token,
+	    github_api_base,
+	    backend,
+	    server_addr,
+	    port_retry_delay,
+	    serve_timeout,
+	    health_check_timeout,
+	    warp_log,
+	    warp_log_format,
+	    access_log,
+	    access_log_format,
+	    api_connect_timeout,
+	    max_assets,
+	    use_browser_download_url,
+	    download_url_override,
+	    legacy_accept_header,
+	    preflight_check,
+	    api_response_max_bytes,
+	    checksum,
+	    user_agent,
+	    tcp_nodelay,
+	    server_header,
+	    hide_server_header,
+	    keep_alive_timeout,
+	    keep_alive_max_requests,
+	    release_check_interval,
+	    max_upstream_fetches,
+	    github_rate_limit,
+	    max_connections,
+	    shutdown_hooks,
+	    shutdown_mode,
+	    root_certificates,
+	    accept_invalid_certs,
+	    max_redirects,
+	    configure_client,
+	    compression,
+	    #[cfg(feature = "brotli")]
+	    brotli_quality,
+	    configure_hyper,
+	    cache_manifest,
+	    manifest_cache_strict,
+	    msix_mode,
+	    asset_idle_timeout,
+	    response_send_timeout,
+	    stream_reconnect,
+	    public_keys,
+	    binary_cache_control,
+	    manifest_cache_control,
+	    forwarded_headers,
+	    mut path_prefixes,
+	    route_prefix,
+	    asset_paths,
+	    #[cfg(target_os = "linux")]
+	    reuse_port,
+	    #[cfg(feature = "mtls")]
+	    mtls,
+	    #[cfg(feature = "cache")]
+	    response_cache,
+	    #[cfg(feature = "cache")]
+	    response_cache_max_file_size,
+	    #[cfg(feature = "cache")]
+	    disk_cache,
+	    #[cfg(feature = "cache")]
+	    cache_dir,
+	    #[cfg(feature = "debug-headers")]
+	    debug_request_headers,
+	    #[cfg(feature = "middleware")]
+	    middleware,
+	    #[cfg(feature = "request-id")]
+	    request_id_generator,
+	} = self;
+
+	let asset_paths = asset_paths.map(|patterns| patterns
+	    .iter()
+	    .map(|pattern| match pattern.trim_matches('/').split('/').collect::<Vec<_>>().split_last() {
+		Some((&"{filename}", directories)) if directories.iter().all(|segment| !segment.is_empty())	=> Ok(directories.iter().map(|segment| segment.to_string()).collect()),
+		_												=> Err(PrivUpdaterError::InvalidPathPattern(pattern.clone())),
+	    })
+	    .collect::<Result<AssetPaths, _>>()
+	).transpose()?;
+	let access_log_file = match access_log {
+	    Some(path)	=> Some(AccessLogFile::open(&path, access_log_format)?),
+	    None	=> None,
+	};
+	let access_log = (warp_log.is_some() || access_log_file.is_some())
+	    .then(|| Arc::new(AccessLog { target: warp_log, format: warp_log_format, file: access_log_file }));
+	let root_certificates = root_certificates
+	    .iter()
+	    .map(|pem| reqwest::Certificate::from_pem(pem))
+	    .collect::<Result<Vec<_>, _>>()?;
+	let cache_control = CacheControl {
+	    binary:	warp::http::HeaderValue::from_str(&binary_cache_control)?,
+	    manifest:	warp::http::HeaderValue::from_str(&manifest_cache_control)?,
+	};
+	let server_header = match hide_server_header {
+	    true	=> None,
+	    false	=> Some(warp::http::HeaderValue::from_str(&server_header.unwrap_or_else(|| format!("priv-tauri-updater/{}", env!("CARGO_PKG_VERSION"))))?),
+	};
+	#[cfg(feature = "mtls")]
+	if mtls.is_some() && configure_hyper.is_some() {
+	    return Err(Box::new(PrivUpdaterError::InvalidTlsConfig(String::from("configure_hyper() can't be combined with mtls()"))));
+	}
+	#[cfg(feature = "mtls")]
+	let client_auth = match mtls {
+	    Some((cert_chain, key, client_ca))	=> Some(Arc::new(tls::ClientAuth::new(&cert_chain, &key, &client_ca)?)),
+	    None				=> None,
+	};
+	let configure_tls = |mut client: reqwest::ClientBuilder| {
+	    for certificate in &root_certificates {
+		client = client.add_root_certificate(certificate.clone());
+	    }
+	    client.danger_accept_invalid_certs(accept_invalid_certs)
+	};
+	let finish = |client: reqwest::ClientBuilder| {
+	    let client = client.redirect(match max_redirects {
+		Some(n)	=> reqwest::redirect::Policy::limited(n),
+		None	=> reqwest::redirect::Policy::none(),
+	    });
+	    match &configure_client {
+		Some(f)	=> f(client).build(),
+		None	=> client.build(),
+	    }
+	};
+
+	#[cfg(not(feature = "middleware"))]
+	let with_middleware = |client: Client| client;
+	#[cfg(feature = "middleware")]
+	let with_middleware = |client: Client| middleware
+	    .iter()
+	    .fold(reqwest_middleware::ClientBuilder::new(client), |client, mw| client.with_arc(mw.clone()))
+	    .build();
+
+	let mut api_client = configure_tls(Client::builder());
+	if let Some(timeout) = api_connect_timeout {
+	    api_client = api_client.connect_timeout(timeout);
+	}
+	let api_client = finish(api_client)?;
+	#[cfg(feature = "cache")]
+	let disk_cache = match disk_cache {
+	    Some(ttl)	=> {
+		let cache_dir = cache_dir.unwrap_or_else(|| {
+		    let cache_dir = std::env::temp_dir().join("priv-tauri-updater").join(format!("{gh_account_name}-{gh_repo_name}"));
+		    #[cfg(feature = "tracing")]
+		    tracing::info!(cache_dir = %cache_dir.display(), "using auto-detected cache directory");
+		    cache_dir
+		});
+		Some(Arc::new(DiskCache::new(cache_dir, ttl)?))
+	    },
+	    None	=> None,
+	};
+	#[cfg(not(feature = "cache"))]
+	let disk_cache = None;
+	let backend = match backend {
+	    Some(backend)	=> backend,
+	    None		=> {
+		let github = GitHubBackend::configured(gh_account_name, gh_repo_name, &gh_token, &github_api_base, user_agent, legacy_accept_header)?;
+		if preflight_check {
+		    backends::preflight_check(&api_client, github.api_base_url(), github.user_agent()).await?;
+		}
+		Arc::new(github)
+	    },
+	};
+	let release_source = ReleaseSource {
+	    client:			with_middleware(api_client),
+	    backend:			backend.clone(),
+	    max_assets,
+	    use_browser_download_url,
+	    max_response_bytes:	api_response_max_bytes,
+	    download_url_override,
+	    token_expires_at:		Mutex::new(None),
+	};
+	let release = release_source.fetch().await?;
+
+	let client = with_middleware(finish(configure_tls(Client::builder())
+	    .default_headers(backend.download_headers())
+	)?);
+
+	#[cfg(feature = "cache")]
+	let response_cache = response_cache
+	    .map(|(capacity, ttl)| Arc::new(ResponseCache::new(capacity, ttl, response_cache_max_file_size)));
+	#[cfg(not(feature = "cache"))]
+	let response_cache = None;
+
+	let updater = PrivUpdater {
+	    server_addr,
+	    port_retry_delay,
+	    serve_timeout,
+	    health_check_timeout,
+	    client,
+	    latest_tag: tokio::sync::watch::channel(release.tag_name.clone()).0,
+	    asset_store: Arc::new(AssetStore::new(release)),
+	    pending_release: Mutex::new(None),
+	    asset_aliases: AssetAliases::default(),
+	    release_source,
+	    access_log,
+	    checksum,
+	    tcp_nodelay,
+	    server_header,
+	    keep_alive_timeout,
+	    keep_alive_max_requests,
+	    release_check_interval,
+	    response_cache,
+	    disk_cache,
+	    upstream_fetches: Arc::new(Semaphore::new(max_upstream_fetches)),
+	    max_upstream_fetches,
+	    rate_limiter:	github_rate_limit.map(|requests_per_minute| Arc::new(GlobalRateLimiter::new(requests_per_minute))),
+	    connections:	Arc::new(Semaphore::new(max_connections.unwrap_or(Semaphore::MAX_PERMITS))),
+	    max_connections,
+	    shutdown_hooks:	Mutex::new(shutdown_hooks),
+	    shutdown_mode,
+	    manifest_encoder: ManifestEncoder {
+		compression,
+		#[cfg(feature = "brotli")]
+		brotli_quality,
+	    },
+	    configure_hyper:	Mutex::new(configure_hyper),
+	    manifest_cache:	cache_manifest.then(ManifestCache::default),
+	    manifest_cache_strict,
+	    msix_mode,
+	    asset_idle_timeout,
+	    response_send_timeout,
+	    stream_reconnect,
+	    public_keys:	Arc::new(public_keys),
+	    cache_control,
+	    forwarded_headers:	forwarded_headers.into(),
+	    path_prefixes:	{
+		// the longest first, so that it's the one stripped
+		path_prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.len()));
+		path_prefixes.into()
+	    },
+	    route_prefix,
+	    asset_paths,
+	    #[cfg(target_os = "linux")]
+	    reuse_port,
+	    #[cfg(feature = "mtls")]
+	    client_auth,
+	    #[cfg(feature = "debug-headers")]
+	    debug_request_headers,
+	    #[cfg(feature = "request-id")]
+	    request_id_generator,
+	};
+	if let Some(cache) = &updater.manifest_cache {
+	    let manifest = updater.fetch_manifest(&updater.asset_store.load()).await?;
+	    *cache.write().unwrap() = manifest;
+	}
+	Ok(updater)
+    }
+    /// Builds the [`PrivUpdater`] and serves the update right away, the configurable [`serve`](crate::serve)
+    ///
+    /// Like `serve()`, installs the `PRIV_UPDATER_DEBUG` subscriber with the `debug-env` feature.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if [`build()`](Self::build) or [`PrivUpdater::serve_update`] do.
+    pub async fn serve(self) -> Result<UpdateServerHandle, Box<dyn Error>> {
+	#[cfg(feature = "debug-env")]
+	init_debug_subscriber();
+	self.build().await?.serve_update().await
+    }
+}
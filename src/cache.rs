@@ -0,0 +1,199 @@
+//! In-memory LRU cache of the proxied responses, and on-disk cache of the assets (requires the `cache` feature)
+
+use crate::ForwardedHeaders;
+#[cfg(feature = "cache")]
+use reqwest::header::{ HeaderName, HeaderValue };
+#[cfg(feature = "cache")]
+use std::{
+    io,
+    num::NonZeroUsize,
+    path::{ Path, PathBuf },
+    sync::atomic::AtomicU64,
+    time::{ Duration, Instant },
+};
+
+/// Hit and miss counts of the response cache, see [`UpdateServerHandle::cache_stats`](crate::UpdateServerHandle::cache_stats)
+#[cfg(feature = "cache")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Requests served from the cache
+    pub hits:	u64,
+    /// Requests that had to be fetched from GitHub
+    pub misses:	u64,
+}
+
+/// When it was inserted, the body and the forwarded headers
+#[cfg(feature = "cache")]
+type Entry = (Instant, Vec<u8>, ForwardedHeaders);
+
+/// Caches `latest.json` (and assets up to `max_file_size` bytes) by filename for `ttl`
+#[cfg(feature = "cache")]
+pub(crate) struct ResponseCache {
+    entries:		std::sync::Mutex<lru::LruCache<String, Entry>>,
+    ttl:		Duration,
+    max_file_size:	usize,
+    hits:		AtomicU64,
+    misses:		AtomicU64,
+}
+
+/// Without the `cache` feature no cache can be constructed
+#[cfg(not(feature = "cache"))]
+pub(crate) enum ResponseCache {}
+
+#[cfg(feature = "cache")]
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration, max_file_size: usize) -> Self {
+	Self {
+	    entries:		std::sync::Mutex::new(lru::LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN))),
+	    ttl,
+	    max_file_size,
+	    hits:		AtomicU64::new(0),
+	    misses:		AtomicU64::new(0),
+	}
+    }
+    pub(crate) fn get(&self, filename: &str) -> Option<(Vec<u8>, ForwardedHeaders)> {
+	let mut entries = self.entries.lock().unwrap();
+	let cached = match entries.get(filename) {
+	    Some((inserted, bytes, headers)) if inserted.elapsed() < self.ttl	=> Some((bytes.clone(), headers.clone())),
+	    Some(_)								=> { entries.pop(filename); None },
+	    None								=> None,
+	};
+	let counter = if cached.is_some() { &self.hits } else { &self.misses };
+	counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+	cached
+    }
+    pub(crate) fn insert(&self, filename: &str, bytes: &[u8], headers: &ForwardedHeaders) {
+	if filename == "latest.json" || bytes.len() <= self.max_file_size {
+	    self.entries.lock().unwrap().put(filename.to_string(), (Instant::now(), bytes.to_vec(), headers.clone()));
+	}
+    }
+    pub(crate) fn clear(&self) {
+	self.entries.lock().unwrap().clear();
+    }
+    pub(crate) fn stats(&self) -> CacheStats {
+	CacheStats {
+	    hits:	self.hits.load(std::sync::atomic::Ordering::Relaxed),
+	    misses:	self.misses.load(std::sync::atomic::Ordering::Relaxed),
+	}
+    }
+}
+
+#[cfg(not(feature = "cache"))]
+impl ResponseCache {
+    pub(crate) fn get(&self, _filename: &str) -> Option<(Vec<u8>, ForwardedHeaders)> {
+	match *self {}
+    }
+    pub(crate) fn insert(&self, _filename: &str, _bytes: &[u8], _headers: &ForwardedHeaders) {
+	match *self {}
+    }
+    pub(crate) fn clear(&self) {
+	match *self {}
+    }
+}
+
+/// Caches the assets on disk for `ttl`, see [`PrivUpdaterBuilder::cache`](crate::PrivUpdaterBuilder::cache)
+///
+/// Every release has its own directory, named after its tag, so an asset of the previous release is never served.
+/// It holds the assets in `assets/` and their forwarded headers in `headers/`, one `name: value` per line.
+#[cfg(feature = "cache")]
+pub(crate) struct DiskCache {
+    dir:	PathBuf,
+    ttl:	Duration,
+}
+
+/// Without the `cache` feature no cache can be constructed
+#[cfg(not(feature = "cache"))]
+pub(crate) enum DiskCache {}
+
+#[cfg(feature = "cache")]
+impl DiskCache {
+    /// Creates `dir` if absent
+    pub(crate) fn new(dir: PathBuf, ttl: Duration) -> io::Result<Self> {
+	std::fs::create_dir_all(&dir)?;
+	Ok( Self { dir, ttl } )
+    }
+    /// `None` for `latest.json`, which is rewritten with the server address, and for the names that aren't plain file names
+    fn paths(&self, tag_name: &str, filename: &str) -> Option<(PathBuf, PathBuf)> {
+	let plain = |name: &str| !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\']);
+	if filename == "latest.json" || !plain(tag_name) || !plain(filename) {
+	    return None;
+	}
+	let release_dir = self.dir.join(tag_name);
+	Some(( release_dir.join("assets").join(filename), release_dir.join("headers").join(filename) ))
+    }
+    pub(crate) async fn get(&self, tag_name: &str, filename: &str) -> Option<(Vec<u8>, ForwardedHeaders)> {
+	let (asset_path, headers_path) = self.paths(tag_name, filename)?;
+	let modified = tokio::fs::metadata(&asset_path).await.ok()?.modified().ok()?;
+	if modified.elapsed().is_ok_and(|age| age >= self.ttl) {
+	    return None;
+	}
+	let headers = tokio::fs::read(&headers_path).await.ok()?;
+	let bytes = tokio::fs::read(&asset_path).await.ok()?;
+	Some(( bytes, parse_headers(&headers) ))
+    }
+    /// Failing to write the cache only costs a download the next time
+    pub(crate) async fn insert(&self, tag_name: &str, filename: &str, bytes: &[u8], headers: &ForwardedHeaders) {
+	let Some((asset_path, headers_path)) = self.paths(tag_name, filename) else {
+	    return;
+	};
+	let serialized = headers
+	    .iter()
+	    .flat_map(|(name, value)| [name.as_str().as_bytes(), b": ", value.as_bytes(), b"\n"].concat())
+	    .collect::<Vec<u8>>();
+	// the headers first: an asset without its headers is a miss
+	if let Err(_e) = async {
+	    write(&headers_path, &serialized).await?;
+	    write(&asset_path, bytes).await
+	}.await {
+	    #[cfg(feature = "tracing")]
+	    tracing::warn!(filename, error = %_e, "failed to write the asset to the disk cache");
+	}
+    }
+    /// Removes everything in the cache directory, the directory itself is kept
+    pub(crate) async fn clear(&self) -> io::Result<()> {
+	let mut entries = tokio::fs::read_dir(&self.dir).await?;
+	while let Some(entry) = entries.next_entry().await? {
+	    if entry.file_type().await?.is_dir() {
+		tokio::fs::remove_dir_all(entry.path()).await?;
+	    } else {
+		tokio::fs::remove_file(entry.path()).await?;
+	    }
+	}
+	Ok(())
+    }
+}
+
+#[cfg(not(feature = "cache"))]
+impl DiskCache {
+    pub(crate) async fn get(&self, _tag_name: &str, _filename: &str) -> Option<(Vec<u8>, ForwardedHeaders)> {
+	match *self {}
+    }
+    pub(crate) async fn insert(&self, _tag_name: &str, _filename: &str, _bytes: &[u8], _headers: &ForwardedHeaders) {
+	match *self {}
+    }
+}
+
+/// Through a temporary file, so that a concurrent `get` never reads a partial file
+#[cfg(feature = "cache")]
+async fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+	tokio::fs::create_dir_all(parent).await?;
+    }
+    static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}-{}.tmp", std::process::id(), TEMP_FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed)));
+    tokio::fs::write(&temp_path, contents).await?;
+    tokio::fs::rename(&temp_path, path).await
+}
+
+#[cfg(feature = "cache")]
+fn parse_headers(serialized: &[u8]) -> ForwardedHeaders {
+    serialized
+	.split(|&byte| byte == b'\n')
+	.filter_map(|line| {
+	    let separator = line.iter().position(|&byte| byte == b':')?;
+	    let value = line[separator + 1..].strip_prefix(b" ").unwrap_or(&line[separator + 1..]);
+	    Some(( HeaderName::from_bytes(&line[..separator]).ok()?, HeaderValue::from_bytes(value).ok()? ))
+	})
+	.collect()
+}
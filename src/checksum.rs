@@ -0,0 +1,134 @@
+//! Verification of downloaded assets against checksum files uploaded alongside them (e.g. `MyApp.msi.sha256`)
+
+use super::*;
+use sha2::Digest;
+
+/// Which checksum sidecar files are used to verify the assets, see [`PrivUpdaterBuilder::verify_checksums`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    /// Requires a `{filename}.sha1` asset next to every served asset
+    ///
+    /// SHA-1 is cryptographically weak, only use it for legacy setups.
+    #[cfg(feature = "sha1")]
+    Sha1,
+    /// Requires a `{filename}.sha256` asset next to every served asset
+    Sha256,
+    /// Uses `{filename}.sha256` if present, then `{filename}.sha1` (with the `sha1` feature),
+    /// otherwise the asset is served unverified
+    Auto,
+}
+
+impl ChecksumAlgorithm {
+    /// The algorithm of the sidecar files, `None` for `Auto` which picks it per asset
+    fn sidecar(self) -> Option<SidecarAlgorithm> {
+	match self {
+	    #[cfg(feature = "sha1")]
+	    Self::Sha1		=> Some(SidecarAlgorithm::Sha1),
+	    Self::Sha256	=> Some(SidecarAlgorithm::Sha256),
+	    Self::Auto		=> None,
+	}
+    }
+}
+
+/// The algorithm of a sidecar file once [`ChecksumAlgorithm::Auto`] is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SidecarAlgorithm {
+    #[cfg(feature = "sha1")]
+    Sha1,
+    Sha256,
+}
+
+impl SidecarAlgorithm {
+    fn extension(self) -> &'static str {
+	match self {
+	    #[cfg(feature = "sha1")]
+	    Self::Sha1		=> "sha1",
+	    Self::Sha256	=> "sha256",
+	}
+    }
+    fn digest(self, bytes: &[u8]) -> String {
+	let digest: Vec<u8> = match self {
+	    #[cfg(feature = "sha1")]
+	    Self::Sha1		=> sha1::Sha1::digest(bytes).to_vec(),
+	    Self::Sha256	=> sha2::Sha256::digest(bytes).to_vec(),
+	};
+	digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+/// An asset that doesn't match its checksum file, or whose required checksum file is missing, answered with a 502
+#[derive(Debug)]
+pub(crate) struct ChecksumRejection {
+    pub(crate) context:	String,
+    pub(crate) message:	String,
+}
+
+impl ChecksumRejection {
+    fn new(filename: &str, message: String) -> Self {
+	Self { context: format!("{filename} checksum"), message }
+    }
+}
+
+impl WarpReject for ChecksumRejection {}
+
+/// Checks `bytes` against the sidecar checksum file of `filename`
+pub(crate) async fn verify(
+    client:	&HttpClient,
+    assets:	&HashMap<String, AssetInfo>,
+    filename:	&str,
+    bytes:	&[u8],
+    algorithm:	ChecksumAlgorithm,
+) -> Result<(), warp::Rejection> {
+    // the sidecar files themselves aren't verified
+    if filename.ends_with(".sha256") || filename.ends_with(".sha1") {
+	return Ok(());
+    }
+    let Some((algorithm, sidecar_url)) = find_sidecar(assets, filename, algorithm)? else {
+	return Ok(());
+    };
+    #[cfg(all(feature = "sha1", feature = "tracing"))]
+    if algorithm == SidecarAlgorithm::Sha1 {
+	tracing::warn!(filename, "verifying with SHA-1, which is deprecated and cryptographically weak; prefer `.sha256` files");
+    }
+
+    let sidecar = get_file(client, sidecar_url)
+	.await
+	.map_err(|e| upstream_rejection(&format!("{filename} checksum"), sidecar_url, e) )?;
+    // `sha256sum` style files are `{checksum}  {filename}`
+    let expected = String::from_utf8_lossy(&sidecar)
+	.split_whitespace()
+	.next()
+	.unwrap_or_default()
+	.to_ascii_lowercase();
+
+    if algorithm.digest(bytes) == expected {
+	Ok(())
+    } else {
+	Err(warp::reject::custom(ChecksumRejection::new(filename, format!("checksum mismatch for '{filename}'"))))
+    }
+}
+
+fn find_sidecar<'a>(
+    assets:	&'a HashMap<String, AssetInfo>,
+    filename:	&str,
+    algorithm:	ChecksumAlgorithm,
+) -> Result<Option<(SidecarAlgorithm, &'a String)>, warp::Rejection> {
+    if let Some(algorithm) = algorithm.sidecar() {
+	return match assets.get(&format!("{filename}.{}", algorithm.extension())) {
+	    Some(sidecar)	=> Ok(Some((algorithm, &sidecar.url))),
+	    None		=> Err(warp::reject::custom(ChecksumRejection::new(filename, format!("no .{} file for '{filename}'", algorithm.extension())))),
+	};
+    }
+    let candidates = [
+	SidecarAlgorithm::Sha256,
+	#[cfg(feature = "sha1")]
+	SidecarAlgorithm::Sha1,
+    ];
+    Ok( candidates
+	.into_iter()
+	.find_map(|candidate| assets
+	    .get(&format!("{filename}.{}", candidate.extension()))
+	    .map(|sidecar| (candidate, &sidecar.url))
+	) )
+}
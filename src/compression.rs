@@ -0,0 +1,91 @@
+//! Compression of the rewritten `latest.json`, see [`PrivUpdaterBuilder::compress_manifest`]
+
+use super::*;
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+use std::io::Write;
+
+/// How `latest.json` is compressed for the clients accepting it, see [`PrivUpdaterBuilder::compress_manifest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Served as is
+    #[default]
+    None,
+    /// `Content-Encoding: gzip` (requires the `gzip` feature)
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// `Content-Encoding: br`, usually smaller than gzip for JSON (requires the `brotli` feature)
+    ///
+    /// The quality is set through [`PrivUpdaterBuilder::brotli_quality`].
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Compression {
+    fn content_encoding(self) -> Option<&'static str> {
+	match self {
+	    Self::None		=> None,
+	    #[cfg(feature = "gzip")]
+	    Self::Gzip		=> Some("gzip"),
+	    #[cfg(feature = "brotli")]
+	    Self::Brotli	=> Some("br"),
+	}
+    }
+}
+
+/// Compresses the manifest responses according to the builder settings
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ManifestEncoder {
+    pub(crate) compression:	Compression,
+    #[cfg(feature = "brotli")]
+    pub(crate) brotli_quality:	u32,
+}
+
+impl ManifestEncoder {
+    /// The manifest reply, compressed if the client's `Accept-Encoding` allows it
+    pub(crate) fn reply(self, manifest: Vec<u8>, accept_encoding: Option<&str>) -> Response<Body> {
+	let Some(encoding) = self.compression.content_encoding() else {
+	    return manifest.into_response();
+	};
+	let accepted = accept_encoding.is_some_and(|header| accepts(header, encoding));
+	let mut reply = match accepted.then(|| self.compress(&manifest)) {
+	    Some(Ok(compressed))	=> {
+		let mut reply = compressed.into_response();
+		reply.headers_mut().insert(warp::http::header::CONTENT_ENCODING, warp::http::HeaderValue::from_static(encoding));
+		reply
+	    },
+	    // compressing into memory can't really fail, but the plain manifest is still a valid answer
+	    _				=> manifest.into_response(),
+	};
+	reply.headers_mut().insert(warp::http::header::VARY, warp::http::HeaderValue::from_static("accept-encoding"));
+	reply
+    }
+    fn compress(self, manifest: &[u8]) -> std::io::Result<Vec<u8>> {
+	match self.compression {
+	    Compression::None		=> Ok(manifest.to_vec()),
+	    #[cfg(feature = "gzip")]
+	    Compression::Gzip		=> {
+		let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+		encoder.write_all(manifest)?;
+		encoder.finish()
+	    },
+	    #[cfg(feature = "brotli")]
+	    Compression::Brotli		=> {
+		let mut compressed = Vec::new();
+		let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, self.brotli_quality, 22);
+		encoder.write_all(manifest)?;
+		drop(encoder);
+		Ok(compressed)
+	    },
+	}
+    }
+}
+
+/// Whether `encoding` is listed in the `Accept-Encoding` header, without a `q=0`
+fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|entry| {
+	let mut params = entry.split(';').map(str::trim);
+	params.next().is_some_and(|name| name.eq_ignore_ascii_case(encoding) || name == "*")
+	    && !params.any(|param| param.strip_prefix("q=").is_some_and(|q| q.parse::<f32>().is_ok_and(|q| q == 0.0)))
+    })
+}
@@ -0,0 +1,272 @@
+//! Serializable settings of a [`PrivUpdaterBuilder`], e.g. to configure the updater from a file
+
+use super::*;
+use serde::Serialize;
+use std::{
+    path::PathBuf,
+    time::Duration,
+};
+
+/// The settings of a [`PrivUpdaterBuilder`] that can be persisted, see [`PrivUpdaterBuilder::config`]
+///
+/// The GitHub token is never part of it, it's passed again to [`to_builder`](Self::to_builder). Neither are the
+/// settings which aren't data: the backend, the shutdown hooks, the certificates (custom roots and mutual TLS), the
+/// `configure_*` closures, the middleware and the request ID generator. Missing fields take the builder defaults when
+/// deserializing, durations are in milliseconds.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use priv_tauri_updater::PrivUpdaterConfig;
+/// # async fn run(config_json: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// // e.g. `{ "gh_account_name": "MyAccount", "gh_repo_name": "MyRepo", "server_addr": "127.0.0.1:8080" }`
+/// let config: PrivUpdaterConfig = serde_json::from_str(config_json)?;
+/// let updater = config.to_builder("MyGitHubToken").build().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivUpdaterConfig {
+    /// See [`PrivUpdaterBuilder::new`]
+    pub gh_account_name:	String,
+    /// See [`PrivUpdaterBuilder::new`]
+    pub gh_repo_name:		String,
+    /// See [`PrivUpdaterBuilder::github_api_base`]
+    pub github_api_base:	String,
+    /// See [`PrivUpdaterBuilder::server_addr`]
+    pub server_addr:		SocketAddr,
+    /// See [`PrivUpdaterBuilder::port_retry_delay`]
+    pub port_retry_delay_ms:	u64,
+    /// See [`PrivUpdaterBuilder::serve_timeout`]
+    pub serve_timeout_ms:	u64,
+    /// See [`PrivUpdaterBuilder::health_check_timeout`]
+    pub health_check_timeout_ms:	u64,
+    /// Target of the request log, see [`PrivUpdaterBuilder::warp_log`]
+    pub warp_log:		Option<String>,
+    /// See [`PrivUpdaterBuilder::warp_log_format`]
+    pub warp_log_format:	LogFormat,
+    /// See [`PrivUpdaterBuilder::access_log`]
+    pub access_log:		Option<PathBuf>,
+    /// See [`PrivUpdaterBuilder::access_log_format`]
+    pub access_log_format:	AccessLogFormat,
+    /// See [`PrivUpdaterBuilder::api_connect_timeout`]
+    pub api_connect_timeout_ms:	Option<u64>,
+    /// See [`PrivUpdaterBuilder::max_assets`]
+    pub max_assets:		Option<usize>,
+    /// See [`PrivUpdaterBuilder::use_browser_download_url`]
+    pub use_browser_download_url:	bool,
+    /// See [`PrivUpdaterBuilder::download_url_override`]
+    pub download_url_override:	Option<String>,
+    /// See [`PrivUpdaterBuilder::use_legacy_accept_header`]
+    pub legacy_accept_header:	bool,
+    /// See [`PrivUpdaterBuilder::preflight_check`]
+    pub preflight_check:	bool,
+    /// See [`PrivUpdaterBuilder::api_response_max_bytes`]
+    pub api_response_max_bytes:	u64,
+    /// See [`PrivUpdaterBuilder::verify_checksums`]
+    pub checksum:		Option<ChecksumAlgorithm>,
+    /// See [`PrivUpdaterBuilder::user_agent`]
+    pub user_agent:		Option<String>,
+    /// See [`PrivUpdaterBuilder::tcp_nodelay`]
+    pub tcp_nodelay:		bool,
+    /// See [`PrivUpdaterBuilder::server_header`], `None` for the default
+    pub server_header:		Option<String>,
+    /// See [`PrivUpdaterBuilder::hide_server_header`]
+    pub hide_server_header:	bool,
+    /// See [`PrivUpdaterBuilder::keep_alive_timeout`]
+    pub keep_alive_timeout_ms:	Option<u64>,
+    /// See [`PrivUpdaterBuilder::keep_alive_max_requests`]
+    pub keep_alive_max_requests:	Option<usize>,
+    /// See [`PrivUpdaterBuilder::release_check_interval`]
+    pub release_check_interval_ms:	Option<u64>,
+    /// See [`PrivUpdaterBuilder::max_concurrent_upstream_fetches`]
+    pub max_upstream_fetches:	usize,
+    /// See [`PrivUpdaterBuilder::github_rate_limit`]
+    pub github_rate_limit:	Option<u32>,
+    /// See [`PrivUpdaterBuilder::max_redirects`], `None` for [`PrivUpdaterBuilder::disable_redirects`]
+    pub max_redirects:		Option<usize>,
+    /// See [`PrivUpdaterBuilder::max_connections`]
+    pub max_connections:	Option<usize>,
+    /// See [`PrivUpdaterBuilder::shutdown_hook_timeout`]
+    pub shutdown_hook_timeout_ms:	u64,
+    /// See [`PrivUpdaterBuilder::shutdown_mode`]
+    pub shutdown_mode:		ShutdownMode,
+    /// See [`PrivUpdaterBuilder::publish_url_to_file`]
+    pub publish_url_to_file:	Option<PathBuf>,
+    /// See [`PrivUpdaterBuilder::compress_manifest`]
+    pub compression:		Compression,
+    /// See [`PrivUpdaterBuilder::brotli_quality`]
+    #[cfg(feature = "brotli")]
+    pub brotli_quality:		u32,
+    /// See [`PrivUpdaterBuilder::cache_manifest`]
+    pub cache_manifest:		bool,
+    /// See [`PrivUpdaterBuilder::serve_manifest_only_from_cache`]
+    pub manifest_cache_strict:	bool,
+    /// See [`PrivUpdaterBuilder::msix_mode`]
+    pub msix_mode:		bool,
+    /// See [`PrivUpdaterBuilder::asset_idle_timeout`]
+    pub asset_idle_timeout_ms:	u64,
+    /// See [`PrivUpdaterBuilder::response_send_timeout`]
+    pub response_send_timeout_ms:	Option<u64>,
+    /// See [`PrivUpdaterBuilder::stream_reconnect`], `None` when disabled
+    pub stream_reconnect:	Option<u8>,
+    /// See [`PrivUpdaterBuilder::inject_public_key`]
+    pub public_key:		Option<String>,
+    /// See [`PrivUpdaterBuilder::inject_additional_public_key`]
+    pub additional_public_keys:	Vec<String>,
+    /// See [`PrivUpdaterBuilder::binary_cache_control`]
+    pub binary_cache_control:	String,
+    /// See [`PrivUpdaterBuilder::manifest_cache_control`]
+    pub manifest_cache_control:	String,
+    /// See [`PrivUpdaterBuilder::forwarded_response_headers`], the invalid header names are ignored
+    pub forwarded_response_headers:	Vec<String>,
+    /// See [`PrivUpdaterBuilder::path_prefix_strip`]
+    pub path_prefixes:		Vec<String>,
+    /// See [`PrivUpdaterBuilder::route_prefix`]
+    pub route_prefix:		String,
+    /// See [`PrivUpdaterBuilder::serve_at_paths`]
+    pub serve_at_paths:		Option<Vec<String>>,
+    /// See [`PrivUpdaterBuilder::reuse_port`]
+    #[cfg(target_os = "linux")]
+    pub reuse_port:		bool,
+    /// Capacity and TTL (in milliseconds) of the [`PrivUpdaterBuilder::response_cache`]
+    #[cfg(feature = "cache")]
+    pub response_cache:		Option<(usize, u64)>,
+    /// See [`PrivUpdaterBuilder::response_cache_max_file_size`]
+    #[cfg(feature = "cache")]
+    pub response_cache_max_file_size:	usize,
+    /// TTL (in milliseconds) of the [`PrivUpdaterBuilder::cache`]
+    #[cfg(feature = "cache")]
+    pub cache_ttl_ms:		Option<u64>,
+    /// See [`PrivUpdaterBuilder::cache_dir`], the temp directory one if `None`
+    #[cfg(feature = "cache")]
+    pub cache_dir:		Option<PathBuf>,
+    /// See [`PrivUpdaterBuilder::debug_request_headers`]
+    #[cfg(feature = "debug-headers")]
+    pub debug_request_headers:	bool,
+}
+
+impl Default for PrivUpdaterConfig {
+    fn default() -> Self {
+	PrivUpdaterBuilder::new("", "", "").config()
+    }
+}
+
+impl PrivUpdaterConfig {
+    /// Reconstructs a builder with these settings, authenticating with `token`
+    #[allow(deprecated)]
+    pub fn to_builder(&self, token: &str) -> PrivUpdaterBuilder {
+	let mut builder = PrivUpdaterBuilder::new(self.gh_account_name.as_str(), self.gh_repo_name.as_str(), token)
+	    .github_api_base(&self.github_api_base)
+	    .server_addr(self.server_addr)
+	    .port_retry_delay(Duration::from_millis(self.port_retry_delay_ms))
+	    .serve_timeout(Duration::from_millis(self.serve_timeout_ms))
+	    .health_check_timeout(Duration::from_millis(self.health_check_timeout_ms))
+	    .use_browser_download_url(self.use_browser_download_url)
+	    .use_legacy_accept_header(self.legacy_accept_header)
+	    .preflight_check(self.preflight_check)
+	    .api_response_max_bytes(self.api_response_max_bytes)
+	    .tcp_nodelay(self.tcp_nodelay)
+	    .hide_server_header(self.hide_server_header)
+	    .max_concurrent_upstream_fetches(self.max_upstream_fetches)
+	    .shutdown_hook_timeout(Duration::from_millis(self.shutdown_hook_timeout_ms))
+	    .shutdown_mode(self.shutdown_mode)
+	    .compress_manifest(self.compression)
+	    .cache_manifest(self.cache_manifest)
+	    .serve_manifest_only_from_cache(self.manifest_cache_strict)
+	    .msix_mode(self.msix_mode)
+	    .warp_log_format(self.warp_log_format.clone())
+	    .access_log_format(self.access_log_format)
+	    .asset_idle_timeout(Duration::from_millis(self.asset_idle_timeout_ms))
+	    .binary_cache_control(&self.binary_cache_control)
+	    .manifest_cache_control(&self.manifest_cache_control)
+	    .route_prefix(&self.route_prefix)
+	    .forwarded_response_headers(self.forwarded_response_headers.iter().filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok()).collect());
+	if let Some(path) = &self.access_log {
+	    builder = builder.access_log(path.clone());
+	}
+	if let Some(path) = &self.publish_url_to_file {
+	    builder = builder.publish_url_to_file(path.clone());
+	}
+	if let Some(target) = &self.warp_log {
+	    builder = builder.warp_log(true, target);
+	}
+	if let Some(timeout) = self.api_connect_timeout_ms {
+	    builder = builder.api_connect_timeout(Duration::from_millis(timeout));
+	}
+	if let Some(base_url) = &self.download_url_override {
+	    builder = builder.download_url_override(base_url);
+	}
+	if let Some(n) = self.max_assets {
+	    builder = builder.max_assets(n);
+	}
+	if let Some(timeout) = self.keep_alive_timeout_ms {
+	    builder = builder.keep_alive_timeout(Duration::from_millis(timeout));
+	}
+	if let Some(timeout) = self.response_send_timeout_ms {
+	    builder = builder.response_send_timeout(Duration::from_millis(timeout));
+	}
+	if let Some(n) = self.keep_alive_max_requests {
+	    builder = builder.keep_alive_max_requests(n);
+	}
+	if let Some(interval) = self.release_check_interval_ms {
+	    builder = builder.release_check_interval(Duration::from_millis(interval));
+	}
+	if let Some(max_retries) = self.stream_reconnect {
+	    builder = builder.stream_reconnect(true, max_retries);
+	}
+	if let Some(requests_per_minute) = self.github_rate_limit {
+	    builder = builder.github_rate_limit(requests_per_minute);
+	}
+	builder = match self.max_redirects {
+	    Some(n)	=> builder.max_redirects(n),
+	    None	=> builder.disable_redirects(),
+	};
+	if let Some(n) = self.max_connections {
+	    builder = builder.max_connections(n);
+	}
+	if let Some(algorithm) = self.checksum {
+	    builder = builder.verify_checksums(algorithm);
+	}
+	if let Some(key) = &self.public_key {
+	    builder = builder.inject_public_key(key);
+	}
+	for key in &self.additional_public_keys {
+	    builder = builder.inject_additional_public_key(key);
+	}
+	if let Some(patterns) = &self.serve_at_paths {
+	    builder = builder.serve_at_paths(patterns.clone());
+	}
+	for prefix in &self.path_prefixes {
+	    builder = builder.path_prefix_strip(prefix);
+	}
+	if let Some(ua) = &self.user_agent {
+	    builder = builder.user_agent(ua);
+	}
+	if let Some(value) = &self.server_header {
+	    builder = builder.server_header(value);
+	}
+	#[cfg(feature = "brotli")]
+	{ builder = builder.brotli_quality(self.brotli_quality); }
+	#[cfg(target_os = "linux")]
+	{ builder = builder.reuse_port(self.reuse_port); }
+	#[cfg(feature = "cache")]
+	if let Some((capacity, ttl)) = self.response_cache {
+	    builder = builder.response_cache(capacity, Duration::from_millis(ttl));
+	}
+	#[cfg(feature = "cache")]
+	{ builder = builder.response_cache_max_file_size(self.response_cache_max_file_size); }
+	#[cfg(feature = "cache")]
+	if let Some(ttl) = self.cache_ttl_ms {
+	    builder = builder.cache(true, Duration::from_millis(ttl));
+	}
+	#[cfg(feature = "cache")]
+	if let Some(cache_dir) = &self.cache_dir {
+	    builder = builder.cache_dir(cache_dir.clone());
+	}
+	#[cfg(feature = "debug-headers")]
+	{ builder = builder.debug_request_headers(self.debug_request_headers); }
+	builder
+    }
+}
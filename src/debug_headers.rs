@@ -0,0 +1,28 @@
+//! Logging of the incoming request headers, see [`PrivUpdaterBuilder::debug_request_headers`]
+
+use super::*;
+use warp::http::header::{ self, HeaderMap as WarpHeaderMap };
+
+const REDACTED_HEADERS: [header::HeaderName; 3] = [header::AUTHORIZATION, header::PROXY_AUTHORIZATION, header::COOKIE];
+
+/// Passes every request through, logging its headers when `enabled`
+pub(crate) fn log_request_headers(enabled: bool) -> impl Filter<Extract = (), Error = Infallible> + Clone {
+    warp::header::headers_cloned()
+	.and(warp::ext::optional::<RemoteAddr>())
+	.map(move |headers: WarpHeaderMap, remote_addr: Option<RemoteAddr>| if enabled {
+	    let headers: Vec<(&str, &str)> = headers
+		.iter()
+		.map(|(name, value)| (
+		    name.as_str(),
+		    if REDACTED_HEADERS.contains(name) { "[REDACTED]" } else { value.to_str().unwrap_or("[non-ascii]") },
+		))
+		.collect();
+	    tracing::trace!(
+		remote_addr = ?remote_addr.map(|RemoteAddr(addr)| addr),
+		timestamp = ?std::time::SystemTime::now(),
+		?headers,
+		"incoming request headers",
+	    );
+	})
+	.untuple_one()
+}
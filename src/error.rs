@@ -0,0 +1,103 @@
+//! Errors specific to `priv_tauri_updater`
+
+use std::{ fmt, time::SystemTime };
+
+/// Errors raised by `priv_tauri_updater` itself (network and header errors are passed through from `reqwest`)
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PrivUpdaterError {
+    /// GitHub refused access to the release with the given token, the message explains which permission is missing
+    InsufficientPermissions(String),
+    /// GitHub refused the request because the primary or secondary rate limit of the token is exhausted
+    GitHubRateLimited {
+	/// When requests are accepted again, `None` if GitHub didn't say
+	reset_at:	Option<SystemTime>,
+    },
+    /// The configured `User-Agent` contains a newline
+    InvalidUserAgent(String),
+    /// The GitHub API root didn't answer with a success, see [`PrivUpdaterBuilder::preflight_check`](crate::PrivUpdaterBuilder::preflight_check)
+    GitHubUnreachable {
+	/// The URL of the GitHub API checked
+	url:		String,
+	/// Why it's unreachable (network error or status)
+	source:		reqwest::Error,
+    },
+    /// The update server couldn't bind a port within the [`PrivUpdaterBuilder::serve_timeout`](crate::PrivUpdaterBuilder::serve_timeout)
+    ServerStartTimeout,
+    /// The [`PrivUpdaterBuilder::mtls`](crate::PrivUpdaterBuilder::mtls) settings can't be used, the message explains why
+    InvalidTlsConfig(String),
+    /// A pattern of [`PrivUpdaterBuilder::serve_at_paths`](crate::PrivUpdaterBuilder::serve_at_paths) doesn't end with `{filename}`
+    InvalidPathPattern(String),
+    /// The download of an asset from GitHub ended before its `content-length`, or isn't of the size of the asset, it's answered with a 502
+    IncompleteDownload {
+	/// The `content-length` announced by GitHub, or the size of the asset
+	expected:	u64,
+	/// The bytes actually received
+	received:	u64,
+    },
+    /// A response of the GitHub API is longer than the [`PrivUpdaterBuilder::api_response_max_bytes`](crate::PrivUpdaterBuilder::api_response_max_bytes)
+    ApiResponseTooLarge {
+	/// The bytes read before giving up, `0` if the `content-length` already exceeded the limit
+	bytes_read:	u64,
+	/// The configured limit
+	limit:		u64,
+    },
+    /// A required environment variable of [`PrivUpdaterBuilder::from_env`](crate::PrivUpdaterBuilder::from_env) isn't set
+    MissingEnvVar(&'static str),
+    /// An environment variable of [`PrivUpdaterBuilder::from_env`](crate::PrivUpdaterBuilder::from_env) can't be parsed
+    InvalidEnvVar {
+	/// The name of the variable
+	name:		&'static str,
+	/// What the value should be, e.g. `a positive integer`
+	expected:	&'static str,
+	/// The value actually set
+	value:		String,
+    },
+}
+
+impl fmt::Display for PrivUpdaterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+	match self {
+	    Self::InsufficientPermissions(message)	=> write!(f, "insufficient permissions: {message}"),
+	    Self::GitHubRateLimited { reset_at: Some(reset_at) }	=> write!(f, "GitHub API rate limit exceeded, it resets at {}", crate::access_log::iso_8601_time(*reset_at)),
+	    Self::GitHubRateLimited { reset_at: None }	=> write!(f, "GitHub API rate limit exceeded"),
+	    Self::InvalidUserAgent(user_agent)		=> write!(f, "invalid User-Agent (contains a newline): {user_agent:?}"),
+	    Self::GitHubUnreachable { url, source }	=> write!(f, "GitHub is unreachable at {url}: {source}"),
+	    Self::ServerStartTimeout			=> write!(f, "timed out starting the update server"),
+	    Self::InvalidTlsConfig(message)		=> write!(f, "invalid TLS configuration: {message}"),
+	    Self::InvalidPathPattern(pattern)		=> write!(f, "invalid path pattern (must end with {{filename}}): {pattern:?}"),
+	    Self::IncompleteDownload { expected, received }	=> write!(f, "incomplete download: received {received} of {expected} bytes"),
+	    Self::ApiResponseTooLarge { bytes_read, limit }	=> write!(f, "GitHub API response too large: read {bytes_read} bytes, the limit is {limit}"),
+	    Self::MissingEnvVar(name)			=> write!(f, "the environment variable {name} is not set"),
+	    Self::InvalidEnvVar { name, expected, value }	=> write!(f, "{name} must be {expected}, got '{value}'"),
+	}
+    }
+}
+
+impl std::error::Error for PrivUpdaterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+	match self {
+	    Self::GitHubUnreachable { source, .. }	=> Some(source),
+	    _					=> None,
+	}
+    }
+}
+
+/// An upstream error together with the operation that caused it
+#[derive(Debug)]
+pub(crate) struct ContextualError {
+    pub(crate) context:	String,
+    pub(crate) source:	Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl fmt::Display for ContextualError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+	write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl std::error::Error for ContextualError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+	Some(self.source.as_ref())
+    }
+}
@@ -0,0 +1,66 @@
+//! Probing GitHub and the update server, see [`UpdateServerHandle::full_health_check`]
+
+use super::*;
+
+/// Result of [`UpdateServerHandle::full_health_check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// `GET /rate_limit` of the GitHub API succeeded (it doesn't count against the rate limit)
+    pub github_reachable:	bool,
+    /// `GET /health` of the update server succeeded
+    pub server_responding:	bool,
+    /// Number of assets of the release being served
+    pub assets_available:	u32,
+    /// Why a probe failed, GitHub's if both did
+    pub last_error:		Option<String>,
+}
+
+impl UpdateServerHandle {
+    /// Probes GitHub and the update server concurrently, each for at most the [`PrivUpdaterBuilder::health_check_timeout`]
+    ///
+    /// Lets a Tauri app tell "GitHub unreachable" from "update server not running" before showing an update prompt.
+    /// With [`PrivUpdaterBuilder::mtls`] the probe has no client certificate, so it only checks that the server
+    /// accepts connections.
+    pub async fn full_health_check(&self) -> HealthStatus {
+	let (updater, timeout) = (&self.updater, self.updater.health_check_timeout);
+	let source = &updater.release_source;
+	let github = probe("GitHub", timeout, source.client
+	    .get(source.backend.health_check_url())
+	    .headers(source.backend.api_headers())
+	    .send());
+	let server = async {
+	    let mut addr = self.bound_addr;
+	    if addr.ip().is_unspecified() {
+		addr.set_ip(match addr {
+		    SocketAddr::V4(_)	=> Ipv4Addr::LOCALHOST.into(),
+		    SocketAddr::V6(_)	=> std::net::Ipv6Addr::LOCALHOST.into(),
+		});
+	    }
+	    if self.scheme == "https" {
+		return match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+		    Ok(Ok(_))	=> Ok(()),
+		    Ok(Err(e))	=> Err(format!("the update server: {e}")),
+		    Err(_)	=> Err(format!("the update server didn't answer within {timeout:?}")),
+		};
+	    }
+	    probe("the update server", timeout, Client::new().get(format!("{}/health", socket_addr_to_url(&addr, self.scheme))).send()).await
+	};
+	let (github, server) = futures_util::future::join(github, server).await;
+	HealthStatus {
+	    github_reachable:	github.is_ok(),
+	    server_responding:	server.is_ok(),
+	    assets_available:	u32::try_from(updater.asset_store.load().assets.len()).unwrap_or(u32::MAX),
+	    last_error:		github.err().or(server.err()),
+	}
+    }
+}
+
+/// `Ok` if `response` is a success within `timeout`, otherwise why not
+async fn probe<E: std::fmt::Display>(what: &str, timeout: Duration, response: impl Future<Output = Result<reqwest::Response, E>>) -> Result<(), String> {
+    match tokio::time::timeout(timeout, response).await {
+	Ok(Ok(response)) if response.status().is_success()	=> Ok(()),
+	Ok(Ok(response))					=> Err(format!("{what} answered {}", response.status())),
+	Ok(Err(e))						=> Err(format!("{what}: {e}")),
+	Err(_)							=> Err(format!("{what} didn't answer within {timeout:?}")),
+    }
+}
@@ -0,0 +1,70 @@
+//! Closing the idle keep-alive connections, see [`PrivUpdaterBuilder::keep_alive_timeout`](crate::PrivUpdaterBuilder::keep_alive_timeout)
+//!
+//! `hyper` neither times out idle connections nor tells when one is idle, so every connection task keeps track of
+//! the requests it is serving itself: the services find the connection they run on through a task local.
+//! The streams of an HTTP/2 connection run in their own tasks, they are counted on the connection they came from.
+
+use std::{
+    future::Future,
+    sync::{ Arc, atomic::{ AtomicBool, AtomicUsize, Ordering } },
+    task::Poll,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static CONNECTION: Arc<ConnectionActivity>;
+}
+
+#[derive(Default)]
+pub(crate) struct ConnectionActivity {
+    /// only the tasks of the connections are, not those of the HTTP/2 streams
+    is_connection:	AtomicBool,
+    in_flight:		AtomicUsize,
+}
+
+/// Marks the current task as a connection, called once hyper starts serving it
+pub(crate) fn connection_started() {
+    let _ = CONNECTION.try_with(|activity| activity.is_connection.store(true, Ordering::Relaxed));
+}
+
+/// A request being served on the connection of the current task, which isn't idle until it's dropped
+pub(crate) struct InFlight(Arc<ConnectionActivity>);
+
+impl InFlight {
+    pub(crate) fn start() -> Option<Self> {
+	CONNECTION.try_with(|activity| {
+	    activity.in_flight.fetch_add(1, Ordering::Relaxed);
+	    Self(activity.clone())
+	}).ok()
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+	self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Runs `connection` until it's done, or until it neither made progress nor served a request for `timeout`
+///
+/// Every poll of the connection counts as progress: hyper only polls it when its socket or a response is ready.
+pub(crate) async fn close_when_idle<F: Future<Output = ()>>(connection: F, timeout: Duration) {
+    let activity = Arc::new(ConnectionActivity::default());
+    let mut connection = std::pin::pin!(CONNECTION.scope(activity.clone(), connection));
+    let mut idle = std::pin::pin!(tokio::time::sleep(timeout));
+    std::future::poll_fn(|cx| {
+	let timed_out = idle.as_mut().poll(cx).is_ready();
+	if timed_out && activity.is_connection.load(Ordering::Relaxed) && activity.in_flight.load(Ordering::Relaxed) == 0 {
+	    #[cfg(feature = "tracing")]
+	    tracing::debug!(timeout_ms = timeout.as_millis() as u64, "closing idle connection");
+	    return Poll::Ready(());
+	}
+	if connection.as_mut().poll(cx).is_ready() {
+	    return Poll::Ready(());
+	}
+	idle.as_mut().reset(Instant::now() + timeout);
+	let _ = idle.as_mut().poll(cx);
+	Poll::Pending
+    }).await
+}
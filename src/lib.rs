@@ -4,7 +4,45 @@
 //! This requires a few changes but it turns out great...
 
 #![warn(missing_docs)]
+mod access_log;
+pub mod backends;
+mod builder;
+mod cache;
+mod checksum;
+mod compression;
+mod config;
+#[cfg(feature = "debug-headers")]
+mod debug_headers;
+mod error;
+mod health;
+mod keep_alive;
+mod manifest;
+mod rate_limit;
+mod reconnect;
+mod release;
+#[cfg(feature = "request-id")]
+mod request_id;
+mod shutdown;
 mod test;
+#[cfg(feature = "mtls")]
+mod tls;
+mod util;
+
+use access_log::{ AccessLog, AccessLogFile };
+use backends::AssetInfo;
+pub use access_log::{ AccessLogEntry, AccessLogFormat, LogFormat };
+pub use builder::PrivUpdaterBuilder;
+#[cfg(feature = "cache")]
+pub use cache::CacheStats;
+pub use checksum::ChecksumAlgorithm;
+pub use compression::Compression;
+use compression::ManifestEncoder;
+pub use config::PrivUpdaterConfig;
+pub use error::PrivUpdaterError;
+pub use health::HealthStatus;
+#[cfg(feature = "nanoid")]
+pub use request_id::nanoid_request_id;
+use error::ContextualError;
 
 use reqwest::{
     header::{
@@ -13,52 +51,162 @@ use reqwest::{
 	HeaderValue,
 	ACCEPT,
 	AUTHORIZATION,
+	ETAG,
+	IF_MODIFIED_SINCE,
+	IF_NONE_MATCH,
+	LAST_MODIFIED,
 	USER_AGENT,
     },
     Client,
 };
 use serde::Deserialize;
+use cache::{ DiskCache, ResponseCache };
+use manifest::PublicKeys;
+use rate_limit::GlobalRateLimiter;
+use reconnect::ReconnectingStream;
+use release::{ AssetStore, Release, ReleaseSource };
+use shutdown::{ ConnectionExecutor, ShutdownHooks };
+pub use shutdown::ShutdownMode;
+use util::socket_addr_to_url;
 use std::{
+    convert::Infallible,
     error::Error,
     collections::HashMap,
     net::{ IpAddr, Ipv4Addr, SocketAddr },
+    path::Path,
+    pin::Pin,
+    sync::{ Arc, Mutex },
+    task::{ Context, Poll },
+    time::{ Duration, SystemTime },
 };
 use warp::{
     Filter, 
+    http::Response,
+    hyper::{
+	self,
+	Body,
+	Server,
+	server::conn::{ AddrIncoming, AddrStream },
+	service::{ Service, make_service_fn, service_fn },
+    },
     reject::Reject as WarpReject,
 };
-use tokio::sync::oneshot;
+use tokio::{
+    sync::{ OwnedSemaphorePermit, Semaphore, oneshot },
+    task::JoinHandle,
+};
 
-#[derive(Debug)]
-#[allow(dead_code)]
-struct ReqwestError(reqwest::Error);
+impl WarpReject for ContextualError {}
+
+/// Client of the requests to GitHub, going through the `with_middleware` stack with the `middleware` feature
+#[cfg(not(feature = "middleware"))]
+type HttpClient = reqwest::Client;
+#[cfg(feature = "middleware")]
+type HttpClient = reqwest_middleware::ClientWithMiddleware;
+
+/// See [`PrivUpdaterBuilder::configure_hyper`]
+type ConfigureHyper = Box<dyn FnOnce(hyper::server::Builder<AddrIncoming>) -> hyper::server::Builder<AddrIncoming> + Send>;
+
+/// The rewritten `latest.json`, see [`PrivUpdaterBuilder::cache_manifest`]
+type ManifestCache = Arc<std::sync::RwLock<Option<Vec<u8>>>>;
+
+/// The patterns of [`PrivUpdaterBuilder::serve_at_paths`] without their final `{filename}`
+type AssetPaths = Arc<[Vec<String>]>;
 
-impl WarpReject for ReqwestError {}
+/// Path requested -> asset served, see [`PrivUpdater::register_asset_alias`]
+type AssetAliases = Arc<std::sync::RwLock<HashMap<String, String>>>;
 
-#[derive(Deserialize)]
-struct GitHubAssetsList {
-    assets: Vec<GitHubAsset>,
+/// The upstream response headers passed on to the client, see [`PrivUpdaterBuilder::forwarded_response_headers`]
+pub(crate) type ForwardedHeaders = Vec<(HeaderName, HeaderValue)>;
+
+/// Number of `GET` requests per asset, see [`UpdateServerHandle::asset_request_count`]
+type RequestCounts = Arc<Mutex<HashMap<String, u64>>>;
+
+/// Address of the client, inserted into the request extensions by the server
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub(crate) struct RemoteAddr(pub(crate) SocketAddr);
+
+/// The connections the server accepts, which know their client
+pub(crate) trait ClientAddr {
+    fn client_addr(&self) -> SocketAddr;
 }
 
-#[derive(Deserialize)]
-struct GitHubAsset {
-    name:			String,
-    url:			String,
-    browser_download_url:	String,
+impl ClientAddr for AddrStream {
+    fn client_addr(&self) -> SocketAddr {
+	self.remote_addr()
+    }
 }
 
 /// Holds all the necessary info to serve a reverse-proxy to your private github repo
 pub struct PrivUpdater {
     server_addr:	SocketAddr,
-    client:		reqwest::Client,
-    assets:		HashMap<String, String>,
-    download_url_base:	String,
-    shutdown_signal:	Option<oneshot::Sender<()>>,
+    client:		HttpClient,
+    asset_store:	Arc<AssetStore>,
+    /// found by the [`PrivUpdaterBuilder::release_check_interval`], served from the next request on
+    pending_release:	Mutex<Option<Release>>,
+    /// see [`PrivUpdater::new_release_receiver`]
+    latest_tag:		tokio::sync::watch::Sender<String>,
+    asset_aliases:	AssetAliases,
+    release_source:	ReleaseSource,
+    port_retry_delay:	Duration,
+    serve_timeout:	Duration,
+    health_check_timeout:	Duration,
+    access_log:		Option<Arc<AccessLog>>,
+    checksum:		Option<ChecksumAlgorithm>,
+    tcp_nodelay:	bool,
+    /// see [`PrivUpdaterBuilder::server_header`], `None` when hidden
+    server_header:	Option<warp::http::HeaderValue>,
+    /// see [`PrivUpdaterBuilder::keep_alive_timeout`]
+    keep_alive_timeout:	Option<Duration>,
+    keep_alive_max_requests:	Option<usize>,
+    release_check_interval:	Option<Duration>,
+    response_cache:	Option<Arc<ResponseCache>>,
+    /// see [`PrivUpdaterBuilder::cache`]
+    disk_cache:		Option<Arc<DiskCache>>,
+    upstream_fetches:	Arc<Semaphore>,
+    max_upstream_fetches:	usize,
+    rate_limiter:	Option<Arc<GlobalRateLimiter>>,
+    /// limits the requests answered at once, see [`PrivUpdaterBuilder::max_connections`] (with [`Semaphore::MAX_PERMITS`]
+    /// when unlimited, so that they're counted all the same)
+    connections:	Arc<Semaphore>,
+    max_connections:	Option<usize>,
+    // behind mutexes (only ever accessed through `&mut self`) so that the updater is `Sync`, see [`UpdateServerHandle::updater`]
+    shutdown_hooks:	Mutex<ShutdownHooks>,
+    shutdown_mode:	ShutdownMode,
+    manifest_encoder:	ManifestEncoder,
+    configure_hyper:	Mutex<Option<ConfigureHyper>>,
+    manifest_cache:	Option<ManifestCache>,
+    manifest_cache_strict:	bool,
+    msix_mode:		bool,
+    asset_idle_timeout:	Duration,
+    /// see [`PrivUpdaterBuilder::response_send_timeout`]
+    response_send_timeout:	Option<Duration>,
+    /// maximum reconnections of an asset download, see [`PrivUpdaterBuilder::stream_reconnect`]
+    stream_reconnect:	Option<u8>,
+    public_keys:	Arc<PublicKeys>,
+    cache_control:	CacheControl,
+    forwarded_headers:	Arc<[HeaderName]>,
+    /// see [`PrivUpdaterBuilder::path_prefix_strip`], the longest first
+    path_prefixes:	Arc<[String]>,
+    /// `/`-separated segments, each starting with a `/`, see [`PrivUpdaterBuilder::route_prefix`]
+    route_prefix:	String,
+    asset_paths:	Option<AssetPaths>,
+    #[cfg(target_os = "linux")]
+    reuse_port:		bool,
+    #[cfg(feature = "mtls")]
+    client_auth:	Option<Arc<tls::ClientAuth>>,
+    #[cfg(feature = "debug-headers")]
+    debug_request_headers:	bool,
+    #[cfg(feature = "request-id")]
+    request_id_generator:	request_id::RequestIdGenerator,
 }
 
 impl PrivUpdater {
     /// Constructs a new `PrivUpdater`
     ///
+    /// See [`PrivUpdaterBuilder`] for the non-default settings.
+    ///
     /// # Arguments
     ///
     /// `gh_account_name` is case-insensitive
@@ -69,181 +217,1264 @@ impl PrivUpdater {
     /// or invalid as HeaderNames (see [reqwest docs](https://docs.rs/reqwest/latest/reqwest/header/struct.HeaderValue.html#method.from_str)).
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,no_run
+    /// # use priv_tauri_updater::PrivUpdater;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
     /// let updater = PrivUpdater::new(
     ///     "MyGitHubAccount",
     ///     "MyGitHubRepo",
     ///     "MyGitHubToken",
-    ///     ([127, 0, 0, 1], 8080)
+    ///     Some(([127, 0, 0, 1], 8080))
     /// ).await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn new<D, S>(gh_account_name: D, gh_repo_name: D, gh_token: D, server_addr: Option<S>) -> Result<Self, Box<dyn Error>>
     where
 	D: std::fmt::Display,
 	S: Into<SocketAddr> + 'static
     {
-	let latest_release_url: String = format!("https://api.github.com/repos/{gh_account_name}/{gh_repo_name}/releases/latest");
-
-	let mut headers = HeaderMap::new();
-	let mut auth_value = HeaderValue::from_str( &format!("Bearer {gh_token}") )?;
-	auth_value.set_sensitive(true);
-	headers.insert(AUTHORIZATION, auth_value);
-	headers.insert(HeaderName::from_static("x-github-api-version"), HeaderValue::from_static( "2022-11-28" ) );
-	headers.insert(USER_AGENT,  HeaderValue::from_str( &format!("{gh_repo_name}") )?);
-
-	let release_info = Client::new().get(latest_release_url)
-	    .headers(headers.clone())
-	    .header(ACCEPT, "application/vnd.github+json")
-	    .send()
-	    .await?
-	    .json::<GitHubAssetsList>()
-	    .await?;
-
-	let download_url_base = release_info.assets[0].browser_download_url.rsplit_once('/').unwrap_or(("", "")).0.to_string();
-
-	let assets = HashMap::<String, String>::from_iter(
-	   release_info
-		.assets
-		.into_iter()
-		.map(|file_info: GitHubAsset| (file_info.name, file_info.url))
-	);
-
-	headers.insert(ACCEPT, HeaderValue::from_static( "application/octet-stream" ));
-	let client = Client::builder()
-	    .default_headers(headers)
-	    .build()?;
-
-	Ok(Self {
-	    server_addr: server_addr
-		.map(|s| Into::<SocketAddr>::into(s))
-		.unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 7748) ),
-	    client,
-	    assets,
-	    download_url_base,
-	    shutdown_signal: None,
+	let mut builder = PrivUpdaterBuilder::new(gh_account_name, gh_repo_name, gh_token);
+	if let Some(server_addr) = server_addr {
+	    builder = builder.server_addr(server_addr);
+	}
+	builder.build().await
+    }
+    /// Fetches the latest release from GitHub again and serves its assets from now on
+    ///
+    /// Requests already being served finish with the previous release, the following ones use the new one,
+    /// and the response cache is cleared. With the `arc-swap` feature the swap never blocks the requests
+    /// looking up an asset, otherwise they wait for the (short) write lock.
+    ///
+    /// # Errors
+    ///
+    /// This function fails like [`PrivUpdaterBuilder::build`], in which case the previous release keeps being served.
+    pub async fn reload_assets(&self) -> Result<(), Box<dyn Error>> {
+	let release = self.release_source.fetch().await?;
+	self.serve_release(release).await
+    }
+    /// Checks whether a new release was published and serves it if so, returning whether it changed
+    ///
+    /// Unlike [`reload_assets`](Self::reload_assets), this asks GitHub whether the release info changed since it was
+    /// last fetched (with its `ETag`), which is cheap and doesn't count against the rate limit when it didn't.
+    /// The release is only replaced if its tag changed. Calling this in a loop is up to the caller:
+    ///
+    /// ```rust,no_run
+    /// # async fn run(updater: priv_tauri_updater::PrivUpdater) -> Result<(), Box<dyn std::error::Error>> {
+    /// loop {
+    ///     tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+    ///     if updater.refresh().await? {
+    ///         println!("now serving a new release");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function fails like [`reload_assets`](Self::reload_assets).
+    pub async fn refresh(&self) -> Result<bool, Box<dyn Error>> {
+	let current = self.asset_store.load();
+	match self.release_source.fetch_if_changed(current.etag.as_deref()).await? {
+	    Some(release) if release.tag_name != current.tag_name	=> {
+		self.serve_release(release).await?;
+		Ok(true)
+	    },
+	    _							=> Ok(false),
+	}
+    }
+    /// The tag of the latest release, which changes as soon as a new one is found
+    ///
+    /// By [`reload_assets`](Self::reload_assets) and [`refresh`](Self::refresh), or by the
+    /// [`PrivUpdaterBuilder::release_check_interval`] before the new release is served, e.g. to tell the user
+    /// that an update is available.
+    pub fn new_release_receiver(&self) -> tokio::sync::watch::Receiver<String> {
+	self.latest_tag.subscribe()
+    }
+    /// Asks GitHub whether a release newer than the served (or already pending) one was published, see
+    /// [`PrivUpdaterBuilder::release_check_interval`]
+    pub(crate) async fn check_release(&self) -> Result<(), Box<dyn Error>> {
+	let etag = match &*self.pending_release.lock().unwrap() {
+	    Some(pending)	=> pending.etag.clone(),
+	    None		=> self.asset_store.load().etag.clone(),
+	};
+	if let Some(release) = self.release_source.fetch_if_changed(etag.as_deref()).await?
+	    && release.tag_name != self.asset_store.load().tag_name
+	{
+	    #[cfg(feature = "tracing")]
+	    tracing::info!(tag = release.tag_name, "new release found, served from the next request on");
+	    self.latest_tag.send_replace(release.tag_name.clone());
+	    *self.pending_release.lock().unwrap() = Some(release);
+	}
+	Ok(())
+    }
+    /// Serves the release found by the last [`check_release`](Self::check_release), if any
+    async fn serve_pending_release(&self) {
+	let Some(release) = self.pending_release.lock().unwrap().take() else {
+	    return;
+	};
+	// the next check finds it again
+	if let Err(_e) = self.serve_release(release).await {
+	    #[cfg(feature = "tracing")]
+	    tracing::warn!(error = %_e, "failed to serve the new release");
+	}
+    }
+    /// Swaps in `release` with its manifest, and clears the response cache
+    async fn serve_release(&self, release: Release) -> Result<(), Box<dyn Error>> {
+	let manifest = match &self.manifest_cache {
+	    Some(_)	=> self.fetch_manifest(&release).await?,
+	    None	=> None,
+	};
+	self.latest_tag.send_if_modified(|tag| {
+	    let changed = *tag != release.tag_name;
+	    tag.clone_from(&release.tag_name);
+	    changed
+	});
+	self.asset_store.replace(release);
+	if let Some(cache) = &self.manifest_cache {
+	    *cache.write().unwrap() = manifest;
+	}
+	if let Some(cache) = &self.response_cache {
+	    cache.clear();
+	}
+	Ok(())
+    }
+    /// The rewritten `latest.json` of `release`, if it has one
+    pub(crate) async fn fetch_manifest(&self, release: &Release) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+	let Some(manifest) = release.assets.get("latest.json") else {
+	    return Ok(None);
+	};
+	let manifest = get_latest_json(&self.client, &manifest.url, &release.download_url_base, &self.assets_url(), &self.public_keys)
+	    .await
+	    .map_err(|e| e as Box<dyn Error>)?;
+	Ok( Some(manifest) )
+    }
+    /// Removes the assets stored on disk by the [`cache`](PrivUpdaterBuilder::cache), keeping those of the other updaters
+    ///
+    /// Does nothing if the cache isn't enabled.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if a cached file can't be removed.
+    #[cfg(feature = "cache")]
+    pub async fn clear_cache(&self) -> Result<(), std::io::Error> {
+	match &self.disk_cache {
+	    Some(cache)	=> cache.clear().await,
+	    None	=> Ok(()),
+	}
+    }
+    /// Tag of the release being served, e.g. `v1.2.0`
+    pub fn release_tag(&self) -> String {
+	self.asset_store.load().tag_name.clone()
+    }
+    /// Names of the release assets being served, sorted
+    pub fn asset_names(&self) -> Vec<String> {
+	let mut names: Vec<String> = self.asset_store.load().assets.keys().cloned().collect();
+	names.sort();
+	names
+    }
+    /// Everything the updater knows about the release being served, for debugging (requires the `debug-introspect` feature)
+    ///
+    /// E.g. `println!("{:#}", updater.dump_assets())`. The asset URLs are the private API ones, don't log them in production.
+    #[cfg(feature = "debug-introspect")]
+    pub fn dump_assets(&self) -> serde_json::Value {
+	let release = self.asset_store.load();
+	serde_json::json!({
+	    "tag_name":			release.tag_name,
+	    "published_at":		release.published_at,
+	    "download_url_base":	release.download_url_base,
+	    "etag":			release.etag,
+	    "assets":			release.assets,
+	    "aliases":			*self.asset_aliases.read().unwrap(),
 	})
     }
+    /// When the release being served was published, parsed from its ISO 8601 `published_at`
+    ///
+    /// `None` for a draft release, or if GitHub sends a timestamp in an unexpected format,
+    /// see [`release_published_at_str`](Self::release_published_at_str).
+    pub fn release_published_at(&self) -> Option<std::time::SystemTime> {
+	release::parse_timestamp(self.asset_store.load().published_at.as_deref()?)
+    }
+    /// The `published_at` of the release being served as sent by GitHub (e.g. `2024-05-01T12:34:56Z`)
+    pub fn release_published_at_str(&self) -> Option<String> {
+	self.asset_store.load().published_at.clone()
+    }
+    /// Serves the asset `actual_filename` at `/{alias}` too, for the download URLs of `latest.json` that don't match
+    /// an asset name
+    ///
+    /// E.g. a universal macOS binary referenced under one URL per architecture. `alias` is a single path segment
+    /// (a leading `/` is ignored), an asset named like it takes precedence. The aliases are kept across
+    /// [`reload_assets`](Self::reload_assets), an alias whose asset isn't in the release is a 404.
+    pub fn register_asset_alias(&self, alias: &str, actual_filename: &str) {
+	self.asset_aliases.write().unwrap().insert(alias.trim_start_matches('/').to_string(), actual_filename.to_string());
+    }
     /// Serve the update at the `server_addr` passed to `PrivUpdater::new()` (default: `127.0.0.1:7748`)
-    pub async fn serve_update(mut self) -> Result<oneshot::Sender<()>, Box<dyn Error>> {
+    ///
+    /// The updater stays reachable through [`UpdateServerHandle::updater`]. Besides the assets, the server answers
+    /// `/robots.txt` with `Disallow: /`: a Tauri app receiving it has the right server address but the wrong path.
+    /// `GET /health` answers `{"status":"ok"}`, see [`UpdateServerHandle::full_health_check`].
+    pub async fn serve_update(self) -> Result<UpdateServerHandle, Box<dyn Error>> {
+	self.serve_routes(None).await
+    }
+    /// Serves the update on an already bound `listener` (e.g. a socket activated by systemd) instead of the `server_addr`
+    ///
+    /// No other port is tried, the handle's [`bound_addr`](UpdateServerHandle::bound_addr) is the address of
+    /// `listener`. A `std::net::TcpListener` must be set non-blocking before being converted with
+    /// `tokio::net::TcpListener::from_std`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the address of `listener` can't be read.
+    pub async fn serve_on_listener(mut self, listener: tokio::net::TcpListener) -> Result<UpdateServerHandle, Box<dyn Error>> {
+	// the URLs of `latest.json` point to the listener
+	self.server_addr = listener.local_addr()?;
+	self.serve_routes(Some(listener)).await
+    }
+    async fn serve_routes(mut self, listener: Option<tokio::net::TcpListener>) -> Result<UpdateServerHandle, Box<dyn Error>> {
 	let (
-	    assets,
+	    asset_store,
+	    asset_aliases,
+	    upstream_fetches,
 	    client,
 	    server_addr,
-	    download_url_base,
+	    public_keys,
+	    rate_limiter,
 	) = (
-	    self.assets.clone(),
+	    self.asset_store.clone(),
+	    self.asset_aliases.clone(),
+	    self.upstream_fetches.clone(),
 	    self.client.clone(),
-	    String::from("http://") + &self.server_addr.to_string(),
-	    self.download_url_base.clone(),
+	    // only known once bound, the port may be retried or picked by the OS
+	    Arc::new(std::sync::OnceLock::<String>::new()),
+	    self.public_keys.clone(),
+	    self.rate_limiter.clone(),
 	);
-	let routes = warp::path::param::<String>()
-	    .and(warp::any().map(move || assets.clone() ))
+	// the asset is looked up before the method filters, so that an unknown asset is a 404 rather than a 405
+	let bound_url = server_addr.clone();
+	let (path_prefixes, asset_paths) = (self.path_prefixes.clone(), self.asset_paths.clone());
+	// only known once the updater is moved into the handle, see [`PrivUpdaterBuilder::release_check_interval`]
+	let served_updater = Arc::new(std::sync::OnceLock::<std::sync::Weak<PrivUpdater>>::new());
+	let route_updater = served_updater.clone();
+	let route_prefix = self.route_prefix
+	    .split('/')
+	    .filter(|segment| !segment.is_empty())
+	    .fold(warp::any().boxed(), |prefix, segment| prefix.and(warp::path(segment.to_string())).boxed());
+	let asset_request = route_prefix
+	    .and(warp::path::tail())
+	    .and_then(move |path: warp::path::Tail| {
+		let filename = asset_filename(&format!("/{}", path.as_str()), &path_prefixes, asset_paths.as_deref()).map(String::from);
+		let updater = route_updater.get().and_then(std::sync::Weak::upgrade);
+		async move {
+		    if let Some(updater) = updater {
+			updater.serve_pending_release().await;
+		    }
+		    filename.ok_or_else(warp::reject::not_found)
+		}
+	    })
+	    .and(warp::any().map(move || asset_store.load() ))
+	    .and(warp::any().map(move || asset_aliases.clone() ))
+	    .and_then(|filename: String, release: Arc<Release>, asset_aliases: AssetAliases| async move {
+		let filename = match asset_aliases.read().unwrap().get(&filename) {
+		    Some(actual) if !release.assets.contains_key(&filename)	=> actual.clone(),
+		    _							=> filename,
+		};
+		match release.assets.get(&filename).cloned() {
+		    Some(asset)	=> Ok((filename, asset, release)),
+		    None	=> {
+			let mut available_assets: Vec<String> = release.assets.keys().cloned().collect();
+			available_assets.sort();
+			Err(warp::reject::custom(UnknownAsset { path: filename, available_assets }))
+		    },
+		}
+	    })
+	    .untuple_one()
+	    .and(warp::any().map(move || upstream_fetches.clone() ))
 	    .and(warp::any().map(move || client.clone() ))
-	    .and(warp::any().map(move || server_addr.clone() ))
-	    .and(warp::any().map(move || download_url_base.clone() ))
+	    .and(warp::any().map(move || server_addr.get().expect("no request is served before the server is bound").clone() ))
+	    .and(warp::any().map(move || public_keys.clone() ))
+	    .and(warp::any().map(move || rate_limiter.clone() ));
+
+	let (checksum, response_cache, manifest_encoder) = (self.checksum, self.response_cache.clone(), self.manifest_encoder);
+	let (manifest_cache, manifest_cache_strict) = (self.manifest_cache.clone(), self.manifest_cache_strict);
+	let (msix_mode, asset_idle_timeout, stream_reconnect) = (self.msix_mode, self.asset_idle_timeout, self.stream_reconnect);
+	let response_send_timeout = self.response_send_timeout;
+	let disk_cache = self.disk_cache.clone();
+	let (cache_control, head_cache_control) = (self.cache_control.clone(), self.cache_control.clone());
+	let (forwarded_headers, head_forwarded_headers) = (self.forwarded_headers.clone(), self.forwarded_headers.clone());
+	let head_manifest_cache = manifest_cache.clone();
+	let request_counts = RequestCounts::default();
+	let route_request_counts = request_counts.clone();
+	// passed on to GitHub when fetching `latest.json`
+	let conditional_headers = warp::header::optional::<String>("if-none-match")
+	    .and(warp::header::optional::<String>("if-modified-since"))
+	    .map(|if_none_match: Option<String>, if_modified_since: Option<String>| [(IF_NONE_MATCH, if_none_match), (IF_MODIFIED_SINCE, if_modified_since)]
+		.into_iter()
+		.filter_map(|(name, value)| Some(( name, HeaderValue::from_str(&value?).ok()? )))
+		.collect::<ForwardedHeaders>()
+	    );
+	let get_route = asset_request.clone()
+	    .and(warp::get())
+	    .and(warp::any().map(move || route_request_counts.clone() ))
+	    .and(warp::any().map(move || checksum ))
+	    .and(warp::any().map(move || response_cache.clone() ))
+	    .and(warp::any().map(move || manifest_cache.clone() ))
+	    .and(warp::header::optional::<String>("accept-encoding"))
+	    .and(conditional_headers)
+	    .and(warp::any().map(move || cache_control.clone() ))
+	    .and(warp::any().map(move || forwarded_headers.clone() ))
 	    .and_then(move |
 		filename:		String,
-		assets:			HashMap<String, String>,
-		client:			Client,
+		asset:			AssetInfo,
+		release:		Arc<Release>,
+		upstream_fetches:	Arc<Semaphore>,
+		client:			HttpClient,
 		server_addr:		String,
-		download_url_base:	String,
-	    | {	async move {
-		let url: &String  = match assets.get(&filename) {
-		    Some(value)	=> value,
-		    None	=> return Err(warp::reject::not_found()),
+		public_keys:		Arc<PublicKeys>,
+		rate_limiter:		Option<Arc<GlobalRateLimiter>>,
+		request_counts:		RequestCounts,
+		checksum:		Option<ChecksumAlgorithm>,
+		response_cache:		Option<Arc<ResponseCache>>,
+		manifest_cache:		Option<ManifestCache>,
+		accept_encoding:	Option<String>,
+		conditions:		ForwardedHeaders,
+		cache_control:		CacheControl,
+		forwarded_headers:	Arc<[HeaderName]>,
+	    | { let disk_cache = disk_cache.clone(); async move {
+		*request_counts.lock().unwrap().entry(filename.clone()).or_default() += 1;
+		let manifest_cache = manifest_cache.filter(|_| filename == "latest.json");
+		let cached_manifest = manifest_cache.as_ref().and_then(|cache| cache.read().unwrap().clone());
+		if manifest_cache.is_some() && cached_manifest.is_none() && manifest_cache_strict {
+		    return Ok(warp::http::StatusCode::SERVICE_UNAVAILABLE.into_response());
+		}
+		let cached = cached_manifest
+		    .map(|bytes| (bytes, ForwardedHeaders::new()))
+		    .or_else(|| response_cache.as_ref().and_then(|cache| cache.get(&filename)));
+		let cached = match cached {
+		    Some(cached)	=> Some(cached),
+		    None		=> OptionFuture::from(disk_cache.as_ref().map(|cache| cache.get(&release.tag_name, &filename))).await.flatten(),
+		};
+		let (bytes, headers) = match cached {
+		    Some(cached)	=> cached,
+		    None		=> {
+			// the checksum file is one more request
+			let requests = if checksum.is_some() && filename != "latest.json" { 2 } else { 1 };
+			if let Some(Err(retry_after)) = OptionFuture::from(rate_limiter.as_ref().map(|limiter| limiter.try_acquire(requests))).await {
+			    return Ok(rate_limit::too_many_requests(retry_after));
+			}
+			let _permit = upstream_fetches.acquire().await.expect("the semaphore is never closed");
+			// the rewritten manifest only keeps the upstream validators, which still change along with it
+			let (bytes, headers) = if filename == "latest.json" {
+			    let (manifest, validators) = get_latest_json_if_modified(&client, &asset.url, &release.download_url_base, &server_addr.to_string(), &public_keys, &conditions)
+				.await
+				.map_err(|e| upstream_rejection(&filename, &asset.url, e) )?;
+			    let Some(bytes) = manifest else {
+				let mut reply = warp::http::StatusCode::NOT_MODIFIED.into_response();
+				forward_headers(&mut reply, &validators);
+				cache_control.apply(&mut reply, &filename);
+				return Ok(reply);
+			    };
+			    (bytes, validators)
+			} else {
+			    let (bytes, headers) = get_asset(&client, &asset, &forwarded_headers, stream_reconnect)
+				.await
+				.map_err(|e| upstream_rejection(&filename, &asset.url, e) )?;
+			    if let Some(algorithm) = checksum {
+				checksum::verify(&client, &release.assets, &filename, &bytes, algorithm).await?;
+			    }
+			    (bytes, headers)
+			};
+			if let Some(cache) = &response_cache {
+			    cache.insert(&filename, &bytes, &headers);
+			}
+			if let Some(cache) = &disk_cache {
+			    cache.insert(&release.tag_name, &filename, &bytes, &headers).await;
+			}
+			if let Some(cache) = &manifest_cache {
+			    *cache.write().unwrap() = Some(bytes.clone());
+			}
+			(bytes, headers)
+		    },
 		};
-		if filename == "latest.json" {
-		    get_latest_json(&client, url, &download_url_base, &server_addr.to_string())
-			.await
-			.map_err(|e| warp::reject::custom(ReqwestError(e)) )
+		// the binary assets are already compressed
+		let mut reply = if filename == "latest.json" {
+		    let mut reply = manifest_encoder.reply(bytes, accept_encoding.as_deref());
+		    forward_headers(&mut reply, &headers);
+		    match response_send_timeout {
+			Some(timeout)	=> send_within(reply, timeout, &filename),
+			None		=> reply,
+		    }
 		} else {
-		    get_file(&client, url)
-			.await
-			.map_err(|e| warp::reject::custom(ReqwestError(e)) )
-		}
+		    let mut reply = asset_reply(&filename, bytes, response_send_timeout.unwrap_or(asset_idle_timeout));
+		    forward_headers(&mut reply, &headers);
+		    set_content_type(&mut reply, &asset);
+		    if msix_mode {
+			add_msix_headers(&mut reply, &filename);
+		    }
+		    reply
+		};
+		cache_control.apply(&mut reply, &filename);
+		Ok::<_, warp::Rejection>(reply)
 	    }});
 
-/*
-	let (tx, rx) = oneshot::channel::<()>();
+	let head_route = asset_request
+	    .and(warp::head())
+	    .and(warp::any().map(move || head_manifest_cache.clone() ))
+	    .and(warp::any().map(move || head_cache_control.clone() ))
+	    .and(warp::any().map(move || head_forwarded_headers.clone() ))
+	    .and_then(move |
+		filename:		String,
+		asset:			AssetInfo,
+		release:		Arc<Release>,
+		upstream_fetches:	Arc<Semaphore>,
+		client:			HttpClient,
+		server_addr:		String,
+		public_keys:		Arc<PublicKeys>,
+		rate_limiter:		Option<Arc<GlobalRateLimiter>>,
+		manifest_cache:		Option<ManifestCache>,
+		cache_control:		CacheControl,
+		forwarded_headers:	Arc<[HeaderName]>,
+	    | {	async move {
+		let cached_manifest = manifest_cache
+		    .filter(|_| filename == "latest.json")
+		    .map(|cache| cache.read().unwrap().as_ref().map(Vec::len));
+		let mut reply = match cached_manifest {
+		    Some(Some(len))			=> head_reply(Some(len.to_string().as_bytes()), Some(b"application/json")),
+		    Some(None) if manifest_cache_strict	=> return Ok(warp::http::StatusCode::SERVICE_UNAVAILABLE.into_response()),
+		    _					=> {
+			if let Some(Err(retry_after)) = OptionFuture::from(rate_limiter.as_ref().map(|limiter| limiter.try_acquire(1))).await {
+			    return Ok(rate_limit::too_many_requests(retry_after));
+			}
+			let _permit = upstream_fetches.acquire().await.expect("the semaphore is never closed");
+			if filename == "latest.json" {
+			    head_latest_json(&client, &asset.url, &release.download_url_base, &server_addr.to_string(), &public_keys)
+				.await
+				.map_err(|e| upstream_rejection(&filename, &asset.url, e) )?
+			} else {
+			    let mut reply = head_file(&client, &asset.url, &forwarded_headers)
+				.await
+				.map_err(|e| upstream_rejection(&filename, &asset.url, e) )?;
+			    set_content_type(&mut reply, &asset);
+			    if msix_mode {
+				add_msix_headers(&mut reply, &filename);
+			    }
+			    reply
+			}
+		    },
+		};
+		cache_control.apply(&mut reply, &filename);
+		Ok::<_, warp::Rejection>(reply)
+	    }});
 
-	let (_addr, server) = warp::serve(routes)
-	    .try_bind_with_graceful_shutdown(self.server_addr, async {
-	         rx.await.ok();
-	    })?;
-*/
-	let (tx, addr, server) = self.serve_with_retry(routes)?;
+	// before the assets, a release asset named `robots.txt` or `health` isn't served
+	let robots_route = warp::path!("robots.txt")
+	    .and(warp::get().or(warp::head()).unify())
+	    .map(|| warp::reply::with_header("User-agent: *\nDisallow: /\n", "content-type", "text/plain"));
+	let health_route = warp::path!("health")
+	    .and(warp::get())
+	    .map(|| warp::reply::json(&serde_json::json!({ "status": "ok" })));
 
-println!("Serving on: {:#?}", addr);
+	// the assets and the other routes are read-only, anything but reading them is rejected before looking them up
+	let allowed_methods = warp::method()
+	    .and_then(|method: warp::http::Method| async move {
+		match method {
+		    warp::http::Method::GET | warp::http::Method::HEAD | warp::http::Method::OPTIONS	=> Ok(()),
+		    _											=> Err(warp::reject::custom(MethodNotAllowed)),
+		}
+	    })
+	    .untuple_one();
+	// not `warp::options()`, whose 405 would win over the 404 of a `GET` outside the `route_prefix`
+	let options_route = warp::method()
+	    .and_then(|method: warp::http::Method| async move {
+		match method {
+		    warp::http::Method::OPTIONS	=> Ok(()),
+		    _				=> Err(warp::reject::not_found()),
+		}
+	    })
+	    .untuple_one()
+	    .map(|| warp::reply::with_header(warp::http::StatusCode::NO_CONTENT, "allow", ALLOWED_METHODS));
 
-	tokio::task::spawn(server);
+	let (connections, max_connections) = (self.connections.clone(), self.max_connections);
+	// the permit is taken before anything else and held until the reply is built, so the upstream fetches count
+	// against the limit; it's never waited for, which would only queue the requests up
+	let connection_limit = warp::any()
+	    .map(move || connections.clone() )
+	    .and_then(move |connections: Arc<Semaphore>| async move {
+		connections
+		    .try_acquire_owned()
+		    .map_err(|_| warp::reject::custom(TooManyConnections { limit: max_connections.unwrap_or(Semaphore::MAX_PERMITS) }))
+	    });
 
-	Ok( tx )	
+	let routes = allowed_methods
+	    .and(connection_limit)
+	    .and(robots_route.or(health_route).or(options_route).or(get_route).or(head_route))
+	    .map(|_permit: OwnedSemaphorePermit, reply| reply)
+	    .recover(recover_rejection);
+	#[cfg(feature = "debug-headers")]
+	let routes = debug_headers::log_request_headers(self.debug_request_headers).and(routes);
+
+	let requested_port = self.server_addr.port();
+	// the shutdown hooks, which remove the file, are moved into the server task
+	let url_file = self.shutdown_hooks.get_mut().unwrap().url_file.clone();
+	let (tx, addr, server) = match listener {
+	    Some(listener)	=> self.serve_incoming(AddrIncoming::from_listener(listener)?, routes.clone()),
+	    None		=> tokio::time::timeout(self.serve_timeout, self.serve_with_retry(routes.clone()))
+		.await
+		.map_err(|_| PrivUpdaterError::ServerStartTimeout)??,
+	};
+	let port_was_retried = self.server_addr.port() != requested_port;
+	self.server_addr = addr;
+	let _ = bound_url.set(self.assets_url());
+	#[cfg(feature = "tracing")]
+	if port_was_retried {
+	    tracing::warn!(original_port = %requested_port, actual_port = %addr.port(), "Port retry occurred");
+	}
+	#[cfg(feature = "tracing")]
+	tracing::info!(%addr, "serving the update server");
+
+	if let Some(path) = &url_file {
+	    publish_url(path, &self.assets_url())?;
+	}
+
+	let scheme = self.scheme();
+	let shutdown_hooks = std::mem::take(self.shutdown_hooks.get_mut().unwrap());
+	let updater = Arc::new(self);
+	let _ = served_updater.set(Arc::downgrade(&updater));
+	let release_checks = updater.release_check_interval.map(|interval| tokio::spawn(release::check_periodically(Arc::downgrade(&updater), interval)));
+	// bound to the same address again
+	let restart = {
+	    let updater = updater.clone();
+	    move || {
+		let incoming = updater.bind().map_err(|e| e.to_string())?;
+		let (tx, _, server) = updater.serve_incoming(incoming, routes.clone());
+		Ok((tx, server))
+	    }
+	};
+	let (shutdown_tx, shutdown_rx) = oneshot::channel();
+	let join_handle = tokio::task::spawn(async move {
+	    shutdown::supervise((tx, server), shutdown_rx, restart, shutdown_hooks).await;
+	    if let Some(release_checks) = release_checks {
+		release_checks.abort();
+	    }
+	});
+
+	Ok( UpdateServerHandle {
+	    bound_addr: addr,
+	    scheme,
+	    requested_port,
+	    port_was_retried,
+	    upstream_fetches: updater.upstream_fetches.clone(),
+	    max_upstream_fetches: updater.max_upstream_fetches,
+	    connections: updater.connections.clone(),
+	    max_connections: updater.max_connections.unwrap_or(Semaphore::MAX_PERMITS),
+	    #[cfg(feature = "cache")]
+	    response_cache: updater.response_cache.clone(),
+	    request_counts,
+	    #[cfg(feature = "mtls")]
+	    client_auth: updater.client_auth.clone(),
+	    shutdown_signal: Mutex::new(Some(shutdown_tx)),
+	    join_handle: Mutex::new(Some(join_handle)),
+	    updater,
+	})
     }
-    /// Shutdown the update server
-    pub fn shutdown(&mut self) {
-	if let Some(sender) = self.shutdown_signal.take() {
-	    let _ = sender.send(());
+    /// The URL of the server address the assets are served under, see [`PrivUpdaterBuilder::route_prefix`]
+    fn assets_url(&self) -> String {
+	format!("{}{}", socket_addr_to_url(&self.server_addr, self.scheme()), self.route_prefix)
+    }
+    /// `https` with [`PrivUpdaterBuilder::mtls`]
+    fn scheme(&self) -> &'static str {
+	#[cfg(feature = "mtls")]
+	if self.client_auth.is_some() {
+	    return "https";
 	}
+	"http"
     }
-    fn serve_with_retry<F>(&mut self, routes: F) -> Result<(oneshot::Sender<()>, SocketAddr, impl Future<Output = ()> + 'static), String>
+    /// Does nothing: the server is shut down through the [`UpdateServerHandle`] returned by [`serve_update`](Self::serve_update)
+    #[deprecated(note = "does nothing, call `UpdateServerHandle::shutdown` on the handle returned by `serve_update()` instead")]
+    pub fn shutdown(&mut self) {}
+    fn bind(&self) -> Result<AddrIncoming, Box<dyn Error>> {
+	#[cfg(target_os = "linux")]
+	if self.reuse_port {
+	    let socket = socket2::Socket::new(socket2::Domain::for_address(self.server_addr), socket2::Type::STREAM, None)?;
+	    socket.set_reuse_port(true)?;
+	    socket.set_nonblocking(true)?;
+	    socket.bind(&self.server_addr.into())?;
+	    socket.listen(1024)?;
+	    return Ok( AddrIncoming::from_listener(tokio::net::TcpListener::from_std(socket.into())?)? );
+	}
+	Ok( AddrIncoming::bind(&self.server_addr)? )
+    }
+    async fn serve_with_retry<F>(&mut self, routes: F) -> Result<(oneshot::Sender<()>, SocketAddr, BoxFuture<'static, ()>), String>
     where
 	F: Filter + Clone + Send + Sync + 'static,
 	F::Extract: Reply,
     {
 	static COUNTER: AtomicU8 = AtomicU8::new(0);
 
+	let incoming = loop {
+	    match self.bind() {
+		Ok(incoming) => break incoming,
+		Err(_) if COUNTER.load(Ordering::Acquire) > 10 => return Err(String::from("Unable to find unused port")),
+		Err(_) => {
+		    self.server_addr.set_port((self.server_addr.port() + 1) % 1000);
+		    COUNTER.fetch_add(1, Ordering::Relaxed);
+		    if !self.port_retry_delay.is_zero() {
+			tokio::time::sleep(self.port_retry_delay).await;
+		    }
+		}
+	    }
+	};
+	Ok( self.serve_incoming(incoming, routes) )
+    }
+    fn serve_incoming<F>(&self, mut incoming: AddrIncoming, routes: F) -> (oneshot::Sender<()>, SocketAddr, BoxFuture<'static, ()>)
+    where
+	F: Filter + Clone + Send + Sync + 'static,
+	F::Extract: Reply,
+    {
 	let (tx, rx) = oneshot::channel::<()>();
+	// warp's own `serve()` always enables TCP_NODELAY, so the hyper server is built here instead
+	incoming.set_nodelay(self.tcp_nodelay);
+	let addr = incoming.local_addr();
+	#[cfg(feature = "mtls")]
+	if let Some(client_auth) = &self.client_auth {
+	    let incoming = tls::TlsIncoming::new(incoming, client_auth.clone());
+	    return ( tx, addr, self.serve_on(Server::builder(incoming), routes, rx) );
+	}
+	let mut server = Server::builder(incoming);
+	if let Some(configure_hyper) = self.configure_hyper.lock().unwrap().take() {
+	    server = configure_hyper(server);
+	}
+	( tx, addr, self.serve_on(server, routes, rx) )
+    }
+    /// Runs the routes on `server` until `rx` signals the shutdown
+    fn serve_on<I, F>(&self, server: hyper::server::Builder<I>, routes: F, rx: oneshot::Receiver<()>) -> BoxFuture<'static, ()>
+    where
+	I: hyper::server::accept::Accept + Send + 'static,
+	I::Conn: ClientAddr + tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+	I::Error: Into<Box<dyn Error + Send + Sync>>,
+	F: Filter + Clone + Send + Sync + 'static,
+	F::Extract: Reply,
+    {
+	let service = warp::service(routes);
+	let (signalled_tx, signalled_rx) = oneshot::channel::<()>();
+	let (close_connections, close) = tokio::sync::watch::channel(false);
+	#[cfg(feature = "request-id")]
+	let request_id_generator = self.request_id_generator.clone();
+	let access_log = self.access_log.clone();
+	let keep_alive_max_requests = self.keep_alive_max_requests;
+	let server_header = self.server_header.clone();
+	let server = server
+	    .executor(ConnectionExecutor { close, idle_timeout: self.keep_alive_timeout })
+	    .serve(make_service_fn(move |conn: &I::Conn| {
+		// warp only knows the remote address when it runs the server itself
+		let (service, remote_addr) = (service.clone(), RemoteAddr(conn.client_addr()));
+		#[cfg(feature = "request-id")]
+		let request_id_generator = request_id_generator.clone();
+		let (access_log, server_header) = (access_log.clone(), server_header.clone());
+		let mut requests_served = 0;
+		async move { keep_alive::connection_started(); Ok::<_, Infallible>(service_fn(move |mut request| {
+		    requests_served += 1;
+		    // HTTP/2 has no `Connection` header
+		    let last_request = keep_alive_max_requests.is_some_and(|max| requests_served >= max)
+			&& request.version() <= warp::http::Version::HTTP_11;
+		    request.extensions_mut().insert(remote_addr);
+		    let log_entry = access_log.as_ref().map(|log| log.start(&request, remote_addr.0));
+		    let in_flight = keep_alive::InFlight::start();
+		    let server_header = server_header.clone();
+		    #[cfg(feature = "request-id")]
+		    let response = request_id::call_with_request_id(service.clone(), request, &request_id_generator);
+		    #[cfg(not(feature = "request-id"))]
+		    let response = service.clone().call(request);
+		    async move {
+			let mut response = response.await?;
+			// once warp has turned the rejections it doesn't recover from into replies too
+			if let Some(server_header) = server_header {
+			    response.headers_mut().insert(warp::http::header::SERVER, server_header);
+			}
+			if last_request {
+			    response.headers_mut().insert(warp::http::header::CONNECTION, warp::http::HeaderValue::from_static("close"));
+			}
+			if let Some(log_entry) = log_entry {
+			    log_entry.finish(&response);
+			}
+			drop(in_flight);
+			Ok::<_, Infallible>(response)
+		    }
+		})) }
+	    }))
+	    .with_graceful_shutdown(async move {
+		rx.await.ok();
+		let _ = signalled_tx.send(());
+	    });
+	let drain_timeout = self.shutdown_mode.drain_timeout();
+	Box::pin(async move {
+	    let mut server = std::pin::pin!(server);
+	    let Some(drain_timeout) = drain_timeout else {
+		let _ = server.await;
+		return;
+	    };
+	    // the graceful shutdown only completes once every connection is closed
+	    if let Either::Right((_, server)) = futures_util::future::select(server.as_mut(), signalled_rx).await
+		&& tokio::time::timeout(drain_timeout, server).await.is_err()
+	    {
+		let _ = close_connections.send(true);
+	    }
+	})
+    }
+}
 
-	if let Ok(( addr, server )) = warp::serve(routes.clone())
-	    .try_bind_with_graceful_shutdown(self.server_addr, async { rx.await.ok(); })
-	{
-	    Ok(( tx, addr, server ))
-	} else if COUNTER.load(Ordering::Acquire) > 10 {
-		Err(String::from("Unable to find unused port"))
-	} else {
-	    self.server_addr.set_port((self.server_addr.port() + 1) % 1000);
-	    COUNTER.fetch_add(1, Ordering::Relaxed);
-	    self.serve_with_retry(routes)
+/// Handle to the running update server, returned by [`PrivUpdater::serve_update`]
+///
+/// The handle is itself a `Future` which resolves once the server task has stopped,
+/// either because [`UpdateServerHandle::shutdown`] was called or because the task panicked.
+/// A server stopping on its own (when hyper fails to accept a connection) is bound and started again, up to
+/// 3 times, without the [`PrivUpdaterBuilder::configure_hyper`] closure which only runs once.
+/// Dropping the handle shuts the server down.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let handle = priv_tauri_updater::serve("MyAccount", "MyRepo", "MyGitHubToken").await?;
+/// handle.await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct UpdateServerHandle {
+    bound_addr:		SocketAddr,
+    scheme:		&'static str,
+    requested_port:	u16,
+    port_was_retried:	bool,
+    upstream_fetches:	Arc<Semaphore>,
+    max_upstream_fetches:	usize,
+    connections:	Arc<Semaphore>,
+    max_connections:	usize,
+    #[cfg(feature = "cache")]
+    response_cache:	Option<Arc<ResponseCache>>,
+    request_counts:	RequestCounts,
+    #[cfg(feature = "mtls")]
+    client_auth:	Option<Arc<tls::ClientAuth>>,
+    shutdown_signal:	Mutex<Option<oneshot::Sender<()>>>,
+    join_handle:	Mutex<Option<JoinHandle<()>>>,
+    updater:		Arc<PrivUpdater>,
+}
+
+impl UpdateServerHandle {
+    /// The address the server is actually bound to (may differ from the requested one after port retries)
+    pub fn bound_addr(&self) -> SocketAddr {
+	self.bound_addr
+    }
+    /// URL of the `latest.json` manifest, to use as the Tauri updater endpoint
+    ///
+    /// Since the port may have been retried, prefer this over hardcoding the endpoint in `tauri.conf.json`,
+    /// e.g. with Tauri v2: `app.updater_builder().endpoints(vec![handle.tauri_endpoint().parse()?])?.build()?`
+    pub fn tauri_endpoint(&self) -> String {
+	format!("{}{}/latest.json", socket_addr_to_url(&self.bound_addr, self.scheme), self.updater.route_prefix)
+    }
+    /// Hit and miss counts of the response cache (all zero when the cache isn't enabled)
+    #[cfg(feature = "cache")]
+    pub fn cache_stats(&self) -> CacheStats {
+	self.response_cache
+	    .as_ref()
+	    .map(|cache| cache.stats())
+	    .unwrap_or(CacheStats { hits: 0, misses: 0 })
+    }
+    /// How many times `filename` was downloaded (`GET` requests, `HEAD` ones aren't counted) since the server started
+    ///
+    /// `latest.json` is counted like any asset, so the update checks and the actual downloads can be told apart.
+    pub fn asset_request_count(&self, filename: &str) -> u64 {
+	self.request_counts.lock().unwrap().get(filename).copied().unwrap_or(0)
+    }
+    /// The [`asset_request_count`](Self::asset_request_count) of every asset requested at least once
+    pub fn all_request_counts(&self) -> HashMap<String, u64> {
+	self.request_counts.lock().unwrap().clone()
+    }
+    /// Number of requests to GitHub currently in flight (at most [`PrivUpdaterBuilder::max_concurrent_upstream_fetches`])
+    pub fn upstream_fetch_concurrency(&self) -> usize {
+	self.max_upstream_fetches - self.upstream_fetches.available_permits()
+    }
+    /// Number of requests the update server is currently answering (at most [`PrivUpdaterBuilder::max_connections`])
+    pub fn active_connections(&self) -> usize {
+	self.max_connections - self.connections.available_permits()
+    }
+    /// When the GitHub token expires, if it has an expiration date (as reported by the last fetch of the release info)
+    ///
+    /// With the `tracing` feature, every fetch of the release info (e.g. [`PrivUpdater::refresh`]) warns when the
+    /// token expires within 24 hours.
+    pub fn token_expires_at(&self) -> Option<SystemTime> {
+	self.updater.release_source.token_expires_at()
+    }
+    /// Whether the requested port was in use, so the server is bound to a different one
+    pub fn port_was_retried(&self) -> bool {
+	self.port_was_retried
+    }
+    /// The port initially requested through the `server_addr`, before any retries
+    pub fn original_requested_port(&self) -> u16 {
+	self.requested_port
+    }
+    /// Trusts the client certificates signed by the CAs of `client_ca_pem` as well, without restarting the server
+    ///
+    /// Applies to the connections made from now on, the open ones are kept. Requires the `mtls` feature.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the PEM doesn't hold valid CA certificates, or with [`PrivUpdaterError::InvalidTlsConfig`]
+    /// if the server wasn't built with [`PrivUpdaterBuilder::mtls`].
+    #[cfg(feature = "mtls")]
+    pub fn add_client_cert(&self, client_ca_pem: &[u8]) -> Result<(), Box<dyn Error>> {
+	match &self.client_auth {
+	    Some(client_auth)	=> client_auth.add_client_ca(client_ca_pem),
+	    None		=> Err(Box::new(PrivUpdaterError::InvalidTlsConfig(String::from("the server doesn't use mutual TLS")))),
+	}
+    }
+    /// The updater being served, e.g. to [`reload_assets`](PrivUpdater::reload_assets) while the server runs
+    ///
+    /// The `Arc` can be cloned into another task, the updater is `Send + Sync`.
+    pub fn updater(&self) -> &Arc<PrivUpdater> {
+	&self.updater
+    }
+    /// Shutdown the update server
+    pub fn shutdown(&self) {
+	if let Some(sender) = self.shutdown_signal.lock().unwrap().take() {
+	    let _ = sender.send(());
+	}
+    }
+    /// Converts the handle into a named [`ServerStopped`] future
+    ///
+    /// Useful where `impl Future` doesn't satisfy the type system (e.g. storing it in a struct field).
+    pub fn into_future(self) -> ServerStopped {
+	ServerStopped {
+	    join_handle: self.join_handle.into_inner().unwrap(),
+	    _shutdown_signal: self.shutdown_signal.into_inner().unwrap(),
 	}
     }
 }
 
+impl Future for UpdateServerHandle {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+	match self.join_handle.lock().unwrap().as_mut() {
+	    Some(join_handle)	=> Pin::new(join_handle).poll(cx).map(|_| ()),
+	    None		=> Poll::Ready(()),
+	}
+    }
+}
+
+/// Future that resolves when the update server stops, see [`UpdateServerHandle::into_future`]
+///
+/// The server keeps running for as long as this future is alive.
+pub struct ServerStopped {
+    join_handle:	Option<JoinHandle<()>>,
+    _shutdown_signal:	Option<oneshot::Sender<()>>,
+}
+
+impl Future for ServerStopped {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+	match self.join_handle.as_mut() {
+	    Some(join_handle)	=> Pin::new(join_handle).poll(cx).map(|_| ()),
+	    None		=> Poll::Ready(()),
+	}
+    }
+}
+
+use futures_util::future::{ BoxFuture, Either, OptionFuture };
 use warp::Reply;
 use std::sync::atomic::{AtomicU8, Ordering};
 
-async fn get_latest_json(client: &Client, url: &str, download_url_base: &str, server_addr: &str) -> Result<Vec<u8>, reqwest::Error> {
-    let text: String = client.get(url).send().await?.text().await?;
-    Ok( text.replace(download_url_base, server_addr).into_bytes() )
+async fn get_latest_json(client: &HttpClient, url: &str, download_url_base: &str, server_addr: &str, public_keys: &PublicKeys) -> Result<Vec<u8>, UpstreamError> {
+    let (manifest, _) = get_latest_json_if_modified(client, url, download_url_base, server_addr, public_keys, &ForwardedHeaders::new()).await?;
+    Ok( manifest.expect("only conditional requests are answered with a 304") )
+}
+
+/// The validators of `latest.json` passed on to the client, which sends them back in its conditional requests
+const MANIFEST_VALIDATORS: [HeaderName; 2] = [ETAG, LAST_MODIFIED];
+
+/// The manifest and its validators, `None` if it still matches the `conditions` (a `304 Not Modified` of GitHub)
+async fn get_latest_json_if_modified(
+    client:		&HttpClient,
+    url:		&str,
+    download_url_base:	&str,
+    server_addr:	&str,
+    public_keys:	&PublicKeys,
+    conditions:		&ForwardedHeaders,
+) -> Result<(Option<Vec<u8>>, ForwardedHeaders), UpstreamError> {
+    let response = client.get(url)
+	.headers(conditions.iter().cloned().collect())
+	.send()
+	.await?;
+    let validators = forwarded_headers(response.headers(), &MANIFEST_VALIDATORS);
+    if !conditions.is_empty() && response.status() == reqwest::StatusCode::NOT_MODIFIED {
+	return Ok(( None, validators ));
+    }
+    let manifest = response.error_for_status()?.bytes().await?;
+    let manifest = manifest::rewrite_urls(&manifest, download_url_base, server_addr)?;
+    Ok(( Some(public_keys.inject(&manifest).transpose()?.unwrap_or(manifest)), validators ))
+}
+
+async fn get_file(client: &HttpClient, url: &str) -> Result<Vec<u8>, UpstreamError> {
+    Ok( client.get(url).send().await?.error_for_status()?.bytes().await?.to_vec() )
+}
+
+/// Downloads `asset`, with its response headers listed in `forwarded`
+///
+/// An error status of GitHub is an error too, rather than its error page being served as the asset.
+///
+/// A body shorter than its `content-length` (e.g. the connection to the CDN dropped) or of another size than the
+/// release says is an error rather than a truncated asset, which the installer could otherwise try to install.
+///
+/// The body is read within the request's future: when the client disconnects, dropping the future drops the
+/// response, whose connection to GitHub is closed instead of being read to the end.
+async fn get_asset(client: &HttpClient, asset: &AssetInfo, forwarded: &[HeaderName], max_reconnects: Option<u8>) -> Result<(Vec<u8>, ForwardedHeaders), UpstreamError> {
+    let response = client.get(&asset.url).send().await?.error_for_status()?;
+    let headers = forwarded_headers(response.headers(), forwarded);
+    let content_length = response.content_length();
+    let bytes = match max_reconnects {
+	Some(max_retries)	=> ReconnectingStream::new(client, &asset.url, response, max_retries).read_to_end().await?,
+	None			=> response.bytes().await?.to_vec(),
+    };
+    for expected in [content_length, Some(asset.size).filter(|&size| size > 0)].into_iter().flatten() {
+	if bytes.len() as u64 != expected {
+	    return Err(Box::new(PrivUpdaterError::IncompleteDownload { expected, received: bytes.len() as u64 }));
+	}
+    }
+    Ok(( bytes, headers ))
+}
+
+/// The content type of `asset` as uploaded, the API downloads are all `application/octet-stream`
+fn set_content_type(reply: &mut Response<Body>, asset: &AssetInfo) {
+    if !asset.content_type.is_empty()
+	&& let Ok(content_type) = warp::http::HeaderValue::from_str(&asset.content_type)
+    {
+	reply.headers_mut().insert(warp::http::header::CONTENT_TYPE, content_type);
+    }
+}
+
+fn forwarded_headers(headers: &HeaderMap, forwarded: &[HeaderName]) -> ForwardedHeaders {
+    forwarded
+	.iter()
+	.flat_map(|name| headers.get_all(name).iter().map(|value| (name.clone(), value.clone())))
+	.collect()
+}
+
+/// Replaces the headers of `reply` by the forwarded ones of the same name
+///
+/// `warp` and `reqwest` depend on different versions of `http`, so the headers are converted through their bytes.
+fn forward_headers(reply: &mut Response<Body>, headers: &ForwardedHeaders) {
+    let converted = headers
+	.iter()
+	.filter_map(|(name, value)| Some(( warp::http::HeaderName::from_bytes(name.as_ref()).ok()?, warp::http::HeaderValue::from_bytes(value.as_bytes()).ok()? )))
+	.collect::<Vec<_>>();
+    for (name, _) in &converted {
+	reply.headers_mut().remove(name);
+    }
+    for (name, value) in converted {
+	reply.headers_mut().append(name, value);
+    }
+}
+
+/// Size of the chunks the binary assets are sent in, see [`asset_reply`]
+const ASSET_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Sends `bytes` chunk by chunk, aborting the response when the client doesn't take the next chunk within `idle_timeout`
+///
+/// The chunks are fed from a separate task: a timer inside the body stream would never fire, since hyper stops
+/// polling the body while the connection can't be written to.
+fn asset_reply(_filename: &str, bytes: Vec<u8>, idle_timeout: Duration) -> Response<Body> {
+    let (mut sender, body) = Body::channel();
+    let mut reply = Response::new(body);
+    reply.headers_mut().insert(warp::http::header::CONTENT_TYPE, warp::http::HeaderValue::from_static("application/octet-stream"));
+    reply.headers_mut().insert(warp::http::header::CONTENT_LENGTH, bytes.len().into());
+    #[cfg(feature = "tracing")]
+    let filename = _filename.to_string();
+    tokio::spawn(async move {
+	let bytes = hyper::body::Bytes::from(bytes);
+	for start in (0..bytes.len()).step_by(ASSET_CHUNK_SIZE) {
+	    let chunk = bytes.slice(start..bytes.len().min(start + ASSET_CHUNK_SIZE));
+	    match tokio::time::timeout(idle_timeout, sender.send_data(chunk)).await {
+		Ok(Ok(()))	=> {},
+		// the connection is already closed
+		Ok(Err(_))	=> return,
+		Err(_)		=> {
+		    #[cfg(feature = "tracing")]
+		    tracing::warn!(filename = %filename, "asset download connection idle timeout");
+		    sender.abort();
+		    return;
+		},
+	    }
+	}
+    });
+    reply
+}
+
+/// Aborts the response when its (already complete) body isn't handed over to hyper within `timeout`
+///
+/// Like in [`asset_reply`], the body is fed from a separate task so that the timer runs while hyper doesn't poll it.
+/// The channel buffers a chunk, so the body is only handed over once the sender is ready again.
+fn send_within(reply: Response<Body>, timeout: Duration, _filename: &str) -> Response<Body> {
+    let (parts, body) = reply.into_parts();
+    let (mut sender, channel) = Body::channel();
+    #[cfg(feature = "tracing")]
+    let filename = _filename.to_string();
+    tokio::spawn(async move {
+	let deadline = tokio::time::Instant::now() + timeout;
+	let Ok(bytes) = hyper::body::to_bytes(body).await else {
+	    return sender.abort();
+	};
+	let handed_over = async {
+	    sender.send_data(bytes).await?;
+	    std::future::poll_fn(|cx| sender.poll_ready(cx)).await
+	};
+	if tokio::time::timeout_at(deadline, handed_over).await.is_err() {
+	    #[cfg(feature = "tracing")]
+	    tracing::warn!(filename = %filename, "response_send_timeout");
+	    sender.abort();
+	}
+    });
+    Response::from_parts(parts, channel)
+}
+
+#[cfg(feature = "debug-env")]
+pub(crate) fn init_debug_subscriber() {
+    use tracing_subscriber::{ filter::Targets, layer::SubscriberExt, util::SubscriberInitExt };
+
+    if std::env::var("PRIV_UPDATER_DEBUG").is_ok_and(|value| value == "1") {
+	let _ = tracing_subscriber::registry()
+	    .with(tracing_subscriber::fmt::layer())
+	    .with(Targets::new().with_target("priv_tauri_updater", tracing::Level::TRACE))
+	    .try_init();
+    }
+}
+
+/// A failed request to GitHub, or an invalid `latest.json`
+type UpstreamError = Box<dyn Error + Send + Sync>;
+
+fn upstream_rejection(filename: &str, url: &str, error: impl Into<UpstreamError>) -> warp::Rejection {
+    warp::reject::custom(ContextualError {
+	context:	format!("fetching asset '{filename}' from '{url}'"),
+	source:		error.into(),
+    })
+}
+
+/// The asset requested by `path` after the longest of `prefixes` it starts with, `None` if it's empty
+///
+/// The first segment without `asset_paths`, otherwise the last one if the others match one of the patterns.
+fn asset_filename<'a>(path: &'a str, prefixes: &[String], asset_paths: Option<&[Vec<String>]>) -> Option<&'a str> {
+    let path = prefixes
+	.iter()
+	.find_map(|prefix| path.strip_prefix(prefix.as_str()).filter(|rest| rest.starts_with('/')))
+	.unwrap_or(path);
+    let mut segments = path.trim_start_matches('/').split('/');
+    let filename = match asset_paths {
+	None		=> segments.next(),
+	Some(patterns)	=> {
+	    let segments: Vec<&str> = segments.collect();
+	    let (filename, directories) = segments.split_last()?;
+	    let matches = |pattern: &Vec<String>| pattern.len() == directories.len() && pattern
+		.iter()
+		.zip(directories)
+		.all(|(expected, segment)| (expected.starts_with('{') && expected.ends_with('}')) || expected == segment);
+	    patterns.iter().any(matches).then_some(*filename)
+	},
+    };
+    filename.filter(|filename| !filename.is_empty())
+}
+
+/// Every permit of the [`PrivUpdaterBuilder::max_connections`] is taken
+#[derive(Debug)]
+struct TooManyConnections {
+    limit:	usize,
+}
+
+impl WarpReject for TooManyConnections {}
+
+/// The methods the update server answers, in the `Allow` header of the `405 Method Not Allowed`
+const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
+/// A request with another method than [`ALLOWED_METHODS`]
+#[derive(Debug)]
+struct MethodNotAllowed;
+
+impl WarpReject for MethodNotAllowed {}
+
+/// No asset of the release is named like the requested path, answered with a 404 listing the assets
+#[derive(Debug, serde::Serialize)]
+struct UnknownAsset {
+    path:		String,
+    available_assets:	Vec<String>,
+}
+
+impl WarpReject for UnknownAsset {}
+
+/// Answers failed upstream fetches with a 502 whose JSON body says what was being fetched, unknown assets with
+/// a 404 listing the available ones, the requests beyond the [`PrivUpdaterBuilder::max_connections`] with a 503 and
+/// the methods not allowed with a 405, other rejections pass through
+async fn recover_rejection(rejection: warp::Rejection) -> Result<Response<Body>, warp::Rejection> {
+    #[derive(serde::Serialize)]
+    struct UpstreamErrorBody<'a> {
+	error:		&'static str,
+	context:	&'a str,
+	message:	String,
+    }
+    #[derive(serde::Serialize)]
+    struct NotFoundBody<'a> {
+	error:		&'static str,
+	#[serde(flatten)]
+	asset:		&'a UnknownAsset,
+    }
+
+    if let Some(asset) = rejection.find::<UnknownAsset>() {
+	return Ok(warp::reply::with_status(
+	    warp::reply::json(&NotFoundBody { error: "not_found", asset }),
+	    warp::http::StatusCode::NOT_FOUND,
+	).into_response());
+    }
+    if let Some(&TooManyConnections { limit }) = rejection.find() {
+	let mut reply = warp::reply::with_status(
+	    warp::reply::json(&serde_json::json!({ "error": "too_many_connections", "limit": limit })),
+	    warp::http::StatusCode::SERVICE_UNAVAILABLE,
+	).into_response();
+	reply.headers_mut().insert(warp::http::header::RETRY_AFTER, 1.into());
+	return Ok(reply);
+    }
+    if let Some(checksum::ChecksumRejection { context, message }) = rejection.find() {
+	return Ok(warp::reply::with_status(
+	    warp::reply::json(&UpstreamErrorBody { error: "checksum_error", context, message: message.clone() }),
+	    warp::http::StatusCode::BAD_GATEWAY,
+	).into_response());
+    }
+    match rejection.find::<ContextualError>() {
+	Some(error)	=> Ok(warp::reply::with_status(
+	    warp::reply::json(&UpstreamErrorBody {
+		error:		"upstream_error",
+		context:	&error.context,
+		message:	error.source.to_string(),
+	    }),
+	    warp::http::StatusCode::BAD_GATEWAY,
+	).into_response()),
+	None if rejection.find::<MethodNotAllowed>().is_some()	=> Ok(warp::reply::with_header(
+	    warp::http::StatusCode::METHOD_NOT_ALLOWED,
+	    "allow",
+	    ALLOWED_METHODS,
+	).into_response()),
+	None		=> Err(rejection),
+    }
+}
+
+/// The rewritten manifest differs in size from the upstream one, so it has to be fetched to answer a `HEAD`
+async fn head_latest_json(client: &HttpClient, url: &str, download_url_base: &str, server_addr: &str, public_keys: &PublicKeys) -> Result<Response<Body>, UpstreamError> {
+    let manifest = get_latest_json(client, url, download_url_base, server_addr, public_keys).await?;
+    Ok( head_reply(
+	Some(manifest.len().to_string().as_bytes()),
+	Some(b"application/json"),
+    ) )
 }
 
-async fn get_file(client: &Client, url: &str) -> Result<Vec<u8>, reqwest::Error> {
-    Ok( client.get(url).send().await?.bytes().await?.to_vec() )
+async fn head_file(client: &HttpClient, url: &str, forwarded: &[HeaderName]) -> Result<Response<Body>, UpstreamError> {
+    let response = client.head(url).send().await?.error_for_status()?;
+    let mut reply = head_reply(None, None);
+    forward_headers(&mut reply, &forwarded_headers(response.headers(), forwarded));
+    Ok( reply )
+}
+
+/// An empty reply to a `HEAD`, with the given `content-length` and `content-type` values (as bytes, see [`forward_headers`])
+fn head_reply(content_length: Option<&[u8]>, content_type: Option<&[u8]>) -> Response<Body> {
+    let mut reply = Response::builder();
+    if let Some(value) = content_length {
+	reply = reply.header(warp::http::header::CONTENT_LENGTH, value);
+    }
+    if let Some(value) = content_type {
+	reply = reply.header(warp::http::header::CONTENT_TYPE, value);
+    }
+    reply.body(Body::empty()).unwrap_or_default()
+}
+
+/// See [`PrivUpdaterBuilder::binary_cache_control`] and [`PrivUpdaterBuilder::manifest_cache_control`]
+#[derive(Clone)]
+pub(crate) struct CacheControl {
+    pub(crate) binary:		warp::http::HeaderValue,
+    pub(crate) manifest:	warp::http::HeaderValue,
+}
+
+impl CacheControl {
+    fn apply(&self, reply: &mut Response<Body>, filename: &str) {
+	let value = if filename == "latest.json" { &self.manifest } else { &self.binary };
+	reply.headers_mut().insert(warp::http::header::CACHE_CONTROL, value.clone());
+    }
+}
+
+/// See [`PrivUpdaterBuilder::msix_mode`]
+fn add_msix_headers(reply: &mut Response<Body>, filename: &str) {
+    let extension = filename.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    if !matches!(extension.as_deref(), Some("msix" | "appx")) {
+	return;
+    }
+    let headers = reply.headers_mut();
+    headers.insert(warp::http::header::CONTENT_TYPE, warp::http::HeaderValue::from_static("application/x-msix"));
+    let disposition = format!("attachment; filename=\"{}\"", filename.replace('\\', "\\\\").replace('"', "\\\""));
+    // asset names GitHub accepts could still be invalid in a header, the download works without it
+    if let Ok(value) = warp::http::HeaderValue::from_str(&disposition) {
+	headers.insert(warp::http::header::CONTENT_DISPOSITION, value);
+    }
 }
 
 /// Convenience method to serve the update immediately at `http://127.0.0.1:7748`
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,no_run
 /// // in `src-tauri/src/lib.rs`
 /// # use std::error::Error;
-/// # use priv_tauri_updater::PrivUpdater;
-/// # use tauri::{AppHandle}
 ///
-/// async fn update(app: tauri::AppHandle) -> Result<(), Box<dyn Error>> {
+/// async fn update() -> Result<(), Box<dyn Error>> {
 ///     let update_server = priv_tauri_updater::serve("MyAccount", "MyRepo", "MyGitHubToken").await?;
 ///
-///     if let Some(update) = app.updater()?.check().await? {
+///     // ... check for the update with Tauri, see below
 ///
-///	// ... your chosen download logic here
+///     // dropping the handle (or calling `update_server.shutdown()`) stops the server
+///     drop(update_server);
 ///
-///     }
+///     Ok(())
+/// }
+/// ```
 ///
-///     update_server.shutdown();
+/// # Tauri integration
 ///
+/// These examples need the app's Tauri dependencies, which this crate doesn't have, so they aren't compiled.
+///
+/// With Tauri v2 and `tauri-plugin-updater`, the endpoint is set from the handle, so that a port retry doesn't
+/// break the update:
+///
+/// ```rust,ignore
+/// use tauri_plugin_updater::UpdaterExt;
+///
+/// async fn update(app: tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+///     let handle = priv_tauri_updater::serve("MyAccount", "MyRepo", "MyGitHubToken").await?;
+///     let updater = app.updater_builder()
+///         .endpoints(vec![handle.tauri_endpoint().parse()?])?
+///         .build()?;
+///     if let Some(update) = updater.check().await? {
+///         update.download_and_install(|_chunk_length, _content_length| {}, || {}).await?;
+///     }
+///     handle.shutdown();
 ///     Ok(())
 /// }
+/// ```
 ///
+/// With Tauri v1 the endpoint is `http://127.0.0.1:7748/latest.json` in the `tauri.updater.endpoints` of
+/// `tauri.conf.json`:
+///
+/// ```rust,ignore
+/// async fn update(app: tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+///     let handle = priv_tauri_updater::serve("MyAccount", "MyRepo", "MyGitHubToken").await?;
+///     let update = app.updater().check().await?;
+///     if update.is_update_available() {
+///         update.download_and_install().await?;
+///     }
+///     handle.shutdown();
+///     Ok(())
+/// }
 /// ```
 ///
 /// # Errors
@@ -253,8 +1484,90 @@ async fn get_file(client: &Client, url: &str) -> Result<Vec<u8>, reqwest::Error>
 /// - `gh_account_name`, `gh_repo_name`, or `gh_token` are incorrect for GitHub or invalid as HeaderNames (see [reqwest docs](https://docs.rs/reqwest/latest/reqwest/header/struct.HeaderValue.html#method.from_str))
 /// - there are network errors (e.g. no internet connection)
 /// - the server address `http://127.0.0.1:7748` is already in use
-pub async fn serve<D: std::fmt::Display>(gh_account_name: D, gh_repo_name: D, gh_token: D) -> Result<oneshot::Sender<()>, Box<dyn Error>> {
+///
+/// # Debugging
+///
+/// With the `debug-env` feature, setting `PRIV_UPDATER_DEBUG=1` installs a `tracing_subscriber::fmt()`
+/// subscriber printing every `priv_tauri_updater` event down to `TRACE` level.
+/// This is meant for quick local debugging only: it does nothing if the app already installed
+/// a global subscriber, and otherwise prevents the app from installing its own afterwards.
+pub async fn serve<D: std::fmt::Display>(gh_account_name: D, gh_repo_name: D, gh_token: D) -> Result<UpdateServerHandle, Box<dyn Error>> {
+    #[cfg(feature = "debug-env")]
+    init_debug_subscriber();
     let updater = PrivUpdater::new(gh_account_name, gh_repo_name, gh_token, None::<([u8; 4], u16)>).await?;
-    let shutdown_signal = updater.serve_update().await?;
-    Ok( shutdown_signal )
-}
\ No newline at end of file
+    let handle = updater.serve_update().await?;
+    Ok( handle )
+}
+
+/// Starts a [`PrivUpdaterBuilder`], for a configured [`serve`]
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let update_server = priv_tauri_updater::builder("MyAccount", "MyRepo", "MyGitHubToken")
+///     .port(8080)
+///     .serve()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn builder<D: std::fmt::Display>(gh_account_name: D, gh_repo_name: D, gh_token: D) -> PrivUpdaterBuilder {
+    PrivUpdaterBuilder::new(gh_account_name, gh_repo_name, gh_token)
+}
+
+/// Replaces the file at `path` by one containing `url`, through a temporary file so it's never read half written
+fn publish_url(path: &Path, url: &str) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    std::fs::write(&temp_path, url)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Reads the URL of an update server published with [`PrivUpdaterBuilder::publish_url_to_file`]
+///
+/// `None` if the file doesn't exist (e.g. the server isn't running) or doesn't hold a server URL.
+pub fn read_server_url(path: &Path) -> Option<String> {
+    let url = std::fs::read_to_string(path).ok()?;
+    let url = url.trim();
+    let addr = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://"))?;
+    addr.parse::<SocketAddr>().ok()?;
+    Some( url.to_string() )
+}
+
+/// Fetches the names of the latest release's assets, sorted, without constructing a [`PrivUpdater`]
+///
+/// This only makes the GitHub API call, e.g. to list the files of the release in a changelog UI.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// for name in priv_tauri_updater::fetch_asset_names("MyAccount", "MyRepo", "MyGitHubToken").await? {
+///     println!("{name}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function fails like [`PrivUpdater::new`].
+pub async fn fetch_asset_names<D: std::fmt::Display>(gh_account_name: D, gh_repo_name: D, gh_token: D) -> Result<Vec<String>, Box<dyn Error>> {
+    let (gh_account_name, gh_repo_name, gh_token) = (gh_account_name.to_string(), gh_repo_name.to_string(), gh_token.to_string());
+    // only wrapped with the `middleware` feature
+    #[cfg_attr(not(feature = "middleware"), allow(clippy::useless_conversion))]
+    let client: HttpClient = Client::new().into();
+    let source = ReleaseSource {
+	client,
+	backend:		Arc::new(backends::GitHubBackend::new(gh_account_name, gh_repo_name, gh_token)?),
+	max_assets:		None,
+	use_browser_download_url:	false,
+	max_response_bytes:	release::DEFAULT_MAX_RESPONSE_BYTES,
+	download_url_override:	None,
+	token_expires_at:	Mutex::new(None),
+    };
+    let mut names: Vec<String> = source.fetch().await?.assets.into_keys().collect();
+    names.sort();
+    Ok( names )
+}
@@ -0,0 +1,207 @@
+//! Rewriting the download URLs of `latest.json` while transcoding it, without building a `serde_json::Value`
+//!
+//! The upstream manifest is deserialized token by token, and every value is serialized again as soon as it's read,
+//! so (apart from the output) only the nesting depth and the string being rewritten are held in memory.
+//! Injecting public keys is the exception, see [`PublicKeys`].
+
+use serde::{
+    de::{ self, DeserializeSeed, MapAccess, SeqAccess },
+    ser::{ self, Serialize, SerializeMap, SerializeSeq },
+};
+use std::{
+    cell::{ Cell, RefCell },
+    fmt,
+};
+
+/// Replaces `download_url_base` by `server_addr` in every string value of the JSON `manifest` (keys are left as is)
+///
+/// Only whole path segments are replaced, a longer URL merely starting with `download_url_base` is kept.
+pub(crate) fn rewrite_urls(manifest: &[u8], download_url_base: &str, server_addr: &str) -> Result<Vec<u8>, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_slice(manifest);
+    let mut output = Vec::with_capacity(manifest.len());
+    Rewrite::new(&mut deserializer, download_url_base, server_addr)
+	.serialize(&mut serde_json::Serializer::new(&mut output))?;
+    deserializer.end()?;
+    Ok( output )
+}
+
+/// The keys injected into `latest.json`, see [`PrivUpdaterBuilder::inject_public_key`](crate::PrivUpdaterBuilder::inject_public_key)
+///
+/// Injecting goes through a `serde_json::Value`, so the object keys of an injected manifest end up sorted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PublicKeys {
+    /// replaces the top-level `pub_key`
+    pub(crate) key:		Option<String>,
+    /// appended to the top-level `pub_keys` array, if there is one
+    pub(crate) additional:	Vec<String>,
+}
+
+impl PublicKeys {
+    /// Injects the keys into the JSON `manifest`, `None` if there are none to inject
+    pub(crate) fn inject(&self, manifest: &[u8]) -> Option<Result<Vec<u8>, serde_json::Error>> {
+	if self.key.is_none() && self.additional.is_empty() {
+	    return None;
+	}
+	#[cfg(feature = "tracing")]
+	tracing::debug!("Injecting public key into manifest");
+	Some( serde_json::from_slice(manifest).and_then(|mut manifest: serde_json::Value| {
+	    if let Some(manifest) = manifest.as_object_mut() {
+		if let Some(key) = &self.key {
+		    manifest.insert(String::from("pub_key"), key.clone().into());
+		}
+		if let Some(keys) = manifest.get_mut("pub_keys").and_then(serde_json::Value::as_array_mut) {
+		    keys.extend(self.additional.iter().cloned().map(serde_json::Value::from));
+		}
+	    }
+	    serde_json::to_vec(&manifest)
+	}) )
+    }
+}
+
+/// Replaces the occurrences of `from` followed by a `/` or the end of `value`, `None` if there are none
+///
+/// Without the boundary check a base ending in `/v1.0` would also match the start of `/v1.0.1/app.msi`.
+fn replace_base(value: &str, from: &str, to: &str) -> Option<String> {
+    if from.is_empty() {
+	return None;
+    }
+    let mut rewritten = String::new();
+    let mut rest = value;
+    let mut replaced = false;
+    while let Some(start) = rest.find(from) {
+	let end = start + from.len();
+	let at_boundary = rest[end..].is_empty() || rest[end..].starts_with('/');
+	rewritten.push_str(&rest[..start]);
+	rewritten.push_str(if at_boundary { to } else { from });
+	replaced |= at_boundary;
+	rest = &rest[end..];
+    }
+    rewritten.push_str(rest);
+    replaced.then_some(rewritten)
+}
+
+/// Serializes the next value of `deserializer`, rewriting its strings on the way
+struct Rewrite<'a, D> {
+    deserializer:	RefCell<Option<D>>,
+    from:		&'a str,
+    to:			&'a str,
+}
+
+impl<'a, D> Rewrite<'a, D> {
+    fn new(deserializer: D, from: &'a str, to: &'a str) -> Self {
+	Self { deserializer: RefCell::new(Some(deserializer)), from, to }
+    }
+}
+
+impl<'de, D: de::Deserializer<'de>> Serialize for Rewrite<'_, D> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+	let deserializer = self.deserializer.borrow_mut().take().expect("a value is only serialized once");
+	// the visitor can only return deserializer errors, so the serializer's own errors are passed back through here
+	let ser_error = Cell::new(None);
+	deserializer
+	    .deserialize_any(Visitor { serializer, from: self.from, to: self.to, ser_error: &ser_error })
+	    .map_err(|e| ser_error.take().unwrap_or_else(|| ser::Error::custom(e)))
+    }
+}
+
+struct Visitor<'a, S: ser::Serializer> {
+    serializer:	S,
+    from:	&'a str,
+    to:		&'a str,
+    ser_error:	&'a Cell<Option<S::Error>>,
+}
+
+/// Stashes a serializer error so it can cross a deserializer boundary
+fn stash<T: ser::Error, E: de::Error>(ser_error: &Cell<Option<T>>, error: T) -> E {
+    let message = error.to_string();
+    ser_error.set(Some(error));
+    E::custom(message)
+}
+
+impl<S: ser::Serializer> Visitor<'_, S> {
+    fn forward<E: de::Error>(ser_error: &Cell<Option<S::Error>>, result: Result<S::Ok, S::Error>) -> Result<S::Ok, E> {
+	result.map_err(|e| stash(ser_error, e))
+    }
+}
+
+impl<'de, S: ser::Serializer> de::Visitor<'de> for Visitor<'_, S> {
+    type Value = S::Ok;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+	f.write_str("any JSON value")
+    }
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<S::Ok, E> {
+	Self::forward(self.ser_error, self.serializer.serialize_bool(v))
+    }
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<S::Ok, E> {
+	Self::forward(self.ser_error, self.serializer.serialize_i64(v))
+    }
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<S::Ok, E> {
+	Self::forward(self.ser_error, self.serializer.serialize_u64(v))
+    }
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<S::Ok, E> {
+	Self::forward(self.ser_error, self.serializer.serialize_f64(v))
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<S::Ok, E> {
+	let result = match replace_base(v, self.from, self.to) {
+	    Some(rewritten)	=> self.serializer.serialize_str(&rewritten),
+	    None		=> self.serializer.serialize_str(v),
+	};
+	Self::forward(self.ser_error, result)
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<S::Ok, E> {
+	Self::forward(self.ser_error, self.serializer.serialize_unit())
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<S::Ok, A::Error> {
+	let mut out = self.serializer.serialize_seq(seq.size_hint()).map_err(|e| stash(self.ser_error, e))?;
+	while seq.next_element_seed(ElementSeed { out: &mut out, from: self.from, to: self.to, ser_error: self.ser_error })?.is_some() {}
+	Self::forward(self.ser_error, out.end())
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<S::Ok, A::Error> {
+	let mut out = self.serializer.serialize_map(map.size_hint()).map_err(|e| stash(self.ser_error, e))?;
+	while map.next_key_seed(EntrySeed { out: &mut out, key: true, from: self.from, to: self.to, ser_error: self.ser_error })?.is_some() {
+	    map.next_value_seed(EntrySeed { out: &mut out, key: false, from: self.from, to: self.to, ser_error: self.ser_error })?;
+	}
+	Self::forward(self.ser_error, out.end())
+    }
+}
+
+/// Transcodes one array element straight into the output array
+struct ElementSeed<'a, O: SerializeSeq> {
+    out:	&'a mut O,
+    from:	&'a str,
+    to:		&'a str,
+    ser_error:	&'a Cell<Option<O::Error>>,
+}
+
+impl<'de, O: SerializeSeq> DeserializeSeed<'de> for ElementSeed<'_, O> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+	self.out
+	    .serialize_element(&Rewrite::new(deserializer, self.from, self.to))
+	    .map_err(|e| stash(self.ser_error, e))
+    }
+}
+
+/// Transcodes one object key (unchanged) or value straight into the output object
+struct EntrySeed<'a, O: SerializeMap> {
+    out:	&'a mut O,
+    key:	bool,
+    from:	&'a str,
+    to:		&'a str,
+    ser_error:	&'a Cell<Option<O::Error>>,
+}
+
+impl<'de, O: SerializeMap> DeserializeSeed<'de> for EntrySeed<'_, O> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+	let result = if self.key {
+	    self.out.serialize_key(&Rewrite::new(deserializer, "", ""))
+	} else {
+	    self.out.serialize_value(&Rewrite::new(deserializer, self.from, self.to))
+	};
+	result.map_err(|e| stash(self.ser_error, e))
+    }
+}
@@ -0,0 +1,49 @@
+//! Limiting the requests to GitHub made by the update server, see [`PrivUpdaterBuilder::github_rate_limit`]
+
+use super::*;
+use std::time::Instant;
+
+/// A token bucket shared by every request of the server: `requests_per_minute` tokens, refilled continuously
+pub(crate) struct GlobalRateLimiter {
+    requests_per_minute:	u32,
+    bucket:			tokio::sync::Mutex<TokenBucket>,
+}
+
+struct TokenBucket {
+    tokens:		f64,
+    refilled_at:	Instant,
+}
+
+impl GlobalRateLimiter {
+    pub(crate) fn new(requests_per_minute: u32) -> Self {
+	let requests_per_minute = requests_per_minute.max(1);
+	Self {
+	    requests_per_minute,
+	    bucket:	tokio::sync::Mutex::new(TokenBucket { tokens: f64::from(requests_per_minute), refilled_at: Instant::now() }),
+	}
+    }
+    /// Takes `n` tokens, or tells how long until there are enough of them
+    pub(crate) async fn try_acquire(&self, n: u32) -> Result<(), Duration> {
+	let capacity = f64::from(self.requests_per_minute);
+	let per_second = capacity / 60.0;
+	let mut bucket = self.bucket.lock().await;
+	let now = Instant::now();
+	bucket.tokens = (bucket.tokens + now.duration_since(bucket.refilled_at).as_secs_f64() * per_second).min(capacity);
+	bucket.refilled_at = now;
+	let n = f64::from(n).min(capacity);
+	if bucket.tokens >= n {
+	    bucket.tokens -= n;
+	    Ok(())
+	} else {
+	    Err(Duration::from_secs_f64((n - bucket.tokens) / per_second))
+	}
+    }
+}
+
+/// `429 Too Many Requests` with the whole seconds until the limiter has a token again
+pub(crate) fn too_many_requests(retry_after: Duration) -> Response<Body> {
+    let mut reply = warp::http::StatusCode::TOO_MANY_REQUESTS.into_response();
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    reply.headers_mut().insert(warp::http::header::RETRY_AFTER, seconds.into());
+    reply
+}
@@ -0,0 +1,75 @@
+//! Resuming the asset downloads whose connection to GitHub drops, see [`PrivUpdaterBuilder::stream_reconnect`]
+
+use super::*;
+
+/// Delay before the first reconnection attempt, doubled for every further one
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(250);
+/// The delay stops doubling there, so that a high `max_retries` doesn't leave the client waiting for hours
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The delay before the reconnection attempt following `retries` failed ones
+pub(crate) fn backoff(retries: u8) -> Duration {
+    RECONNECT_BACKOFF
+	.saturating_mul(2_u32.saturating_pow(retries.into()))
+	.min(MAX_RECONNECT_BACKOFF)
+}
+
+/// The body of an asset download, reconnecting with a `Range` request from the bytes received so far when it fails
+///
+/// A CDN answering the `Range` request with a `200 OK` instead of a `206 Partial Content` sends the whole file
+/// again, which then replaces the bytes received so far.
+pub(crate) struct ReconnectingStream<'a> {
+    client:		&'a HttpClient,
+    url:		&'a str,
+    response:		reqwest::Response,
+    max_retries:	u8,
+}
+
+impl<'a> ReconnectingStream<'a> {
+    pub(crate) fn new(client: &'a HttpClient, url: &'a str, response: reqwest::Response, max_retries: u8) -> Self {
+	Self { client, url, response, max_retries }
+    }
+    pub(crate) async fn read_to_end(mut self) -> Result<Vec<u8>, UpstreamError> {
+	let mut bytes = Vec::new();
+	let mut retries = 0;
+	loop {
+	    let mut error: UpstreamError = match self.response.chunk().await {
+		Ok(Some(chunk))	=> { bytes.extend_from_slice(&chunk); continue; },
+		Ok(None)	=> return Ok(bytes),
+		Err(e)		=> Box::new(e),
+	    };
+	    self.response = loop {
+		if retries == self.max_retries {
+		    return Err(error);
+		}
+		#[cfg(feature = "tracing")]
+		tracing::warn!(url = self.url, received = bytes.len(), %error, "asset download interrupted; reconnecting");
+		tokio::time::sleep(backoff(retries)).await;
+		retries += 1;
+		match self.resume(bytes.len()).await {
+		    Ok(response)	=> break response,
+		    Err(e)		=> error = e,
+		}
+	    };
+	    if self.response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+		bytes.clear();
+	    }
+	}
+    }
+    /// The rest of the file from `offset` on, or the whole file if the server doesn't support `Range` requests
+    async fn resume(&self, offset: usize) -> Result<reqwest::Response, UpstreamError> {
+	let response = self.client.get(self.url)
+	    .header(reqwest::header::RANGE, format!("bytes={offset}-"))
+	    .send()
+	    .await?
+	    .error_for_status()?;
+	let resumed_at_offset = response.headers()
+	    .get(reqwest::header::CONTENT_RANGE)
+	    .and_then(|value| value.to_str().ok())
+	    .is_some_and(|range| range.starts_with(&format!("bytes {offset}-")));
+	if response.status() == reqwest::StatusCode::PARTIAL_CONTENT && !resumed_at_offset {
+	    return Err(format!("the server resumed {} from another offset than {offset}", self.url).into());
+	}
+	Ok( response )
+    }
+}
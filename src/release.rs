@@ -0,0 +1,261 @@
+//! Fetching the latest release from its backend, and the store its assets are served from
+//!
+//! By default the served release sits behind a `RwLock`, so a [`PrivUpdater::reload_assets`] briefly blocks
+//! the requests looking up an asset. With the `arc-swap` feature the release is swapped atomically instead
+//! and readers are never blocked, at the cost of an extra dependency.
+//! Either way a request sees the old or the new release as a whole, never a mix of both.
+
+use super::*;
+use backends::{ AssetInfo, Backend, BackendRelease };
+use std::time::SystemTime;
+
+/// The assets of the release being served
+pub(crate) struct Release {
+    /// filename -> asset, its `url` being the one downloaded from (see [`PrivUpdaterBuilder::use_browser_download_url`])
+    pub(crate) assets:			HashMap<String, AssetInfo>,
+    /// replaced by the server address in `latest.json`
+    pub(crate) download_url_base:	String,
+    pub(crate) tag_name:		String,
+    /// ISO 8601, `None` for a draft
+    pub(crate) published_at:		Option<String>,
+    /// of the release info response, to ask GitHub whether it changed since
+    pub(crate) etag:			Option<String>,
+}
+
+/// The default of [`PrivUpdaterBuilder::api_response_max_bytes`]
+pub(crate) const DEFAULT_MAX_RESPONSE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Everything needed to (re)fetch the latest release
+pub(crate) struct ReleaseSource {
+    pub(crate) client:			HttpClient,
+    /// see [`PrivUpdaterBuilder::backend`]
+    pub(crate) backend:			Arc<dyn Backend>,
+    pub(crate) max_assets:		Option<usize>,
+    pub(crate) use_browser_download_url:	bool,
+    /// see [`PrivUpdaterBuilder::api_response_max_bytes`]
+    pub(crate) max_response_bytes:	u64,
+    /// replaces the `download_url_base` derived from the assets, see [`PrivUpdaterBuilder::download_url_override`]
+    pub(crate) download_url_override:	Option<String>,
+    /// as last reported by GitHub, see [`UpdateServerHandle::token_expires_at`]
+    pub(crate) token_expires_at:	Mutex<Option<SystemTime>>,
+}
+
+impl ReleaseSource {
+    pub(crate) async fn fetch(&self) -> Result<Release, Box<dyn Error>> {
+	Ok( self.fetch_if_changed(None).await?.expect("only conditional requests are answered with a 304") )
+    }
+    /// `None` if the release info still matches `etag` (a `304 Not Modified`, which doesn't count against the rate limit)
+    pub(crate) async fn fetch_if_changed(&self, etag: Option<&str>) -> Result<Option<Release>, Box<dyn Error>> {
+	let mut request = self.client.get(self.backend.latest_release_url())
+	    .headers(self.backend.api_headers());
+	if let Some(etag) = etag {
+	    request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+	}
+	let response = request.send().await?;
+	#[cfg(feature = "tracing")]
+	warn_on_api_sunset(response.headers());
+	self.record_token_expiration(response.headers());
+	if response.status() == reqwest::StatusCode::FORBIDDEN || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+	    // GitHub answers the exhausted rate limits with a 403 too, the token isn't to blame then
+	    if let Some(reset_at) = rate_limit_reset(response.headers()) {
+		return Err(Box::new(PrivUpdaterError::GitHubRateLimited { reset_at }));
+	    }
+	    if response.status() == reqwest::StatusCode::FORBIDDEN {
+		return Err(Box::new(PrivUpdaterError::InsufficientPermissions(self.backend.missing_permission_hint())));
+	    }
+	}
+	if etag.is_some() && response.status() == reqwest::StatusCode::NOT_MODIFIED {
+	    return Ok(None);
+	}
+	let etag = response.headers()
+	    .get(reqwest::header::ETAG)
+	    .and_then(|value| value.to_str().ok())
+	    .map(str::to_string);
+	let release_info = self.backend.parse_release(&read_limited(response, self.max_response_bytes).await?).map_err(|e| e as Box<dyn Error>)?;
+
+	let download_url_base = match (&self.download_url_override, release_info.assets.first()) {
+	    (Some(base), _)	=> base.clone(),
+	    (None, Some(asset))	=> asset.browser_download_url.rsplit_once('/').unwrap_or(("", "")).0.to_string(),
+	    (None, None)	=> String::new(),
+	};
+
+	let BackendRelease { tag_name, published_at, assets: mut release_assets } = release_info;
+	if let Some(max_assets) = self.max_assets
+	    && release_assets.len() > max_assets
+	{
+	    #[cfg(feature = "tracing")]
+	    tracing::warn!(asset_count = release_assets.len(), max_assets, "release has more assets than the configured limit; truncating");
+	    release_assets.sort_by(|a, b| a.name.cmp(&b.name));
+	    release_assets.truncate(max_assets);
+	}
+
+	let assets = HashMap::<String, AssetInfo>::from_iter(
+	   release_assets
+		.into_iter()
+		.map(|mut file_info: AssetInfo| {
+		    if self.use_browser_download_url {
+			file_info.url = file_info.browser_download_url.clone();
+		    }
+		    (file_info.name.clone(), file_info)
+		})
+	);
+
+	Ok(Some(Release {
+	    assets,
+	    download_url_base,
+	    tag_name,
+	    published_at,
+	    etag,
+	}))
+    }
+    /// Only tokens with an expiration date report it, in the `github-authentication-token-expiration` header
+    fn record_token_expiration(&self, headers: &HeaderMap) {
+	let Some(expires_at) = headers
+	    .get("github-authentication-token-expiration")
+	    .and_then(|value| value.to_str().ok())
+	    .and_then(parse_token_expiration)
+	else {
+	    return;
+	};
+	#[cfg(feature = "tracing")]
+	if expires_at < SystemTime::now() + TOKEN_EXPIRY_WARNING {
+	    tracing::warn!(expires_at = ?expires_at, "GitHub token expires soon; rotate the token");
+	}
+	*self.token_expires_at.lock().unwrap() = Some(expires_at);
+    }
+    pub(crate) fn token_expires_at(&self) -> Option<SystemTime> {
+	*self.token_expires_at.lock().unwrap()
+    }
+}
+
+/// `Some` if the headers of a refused request say a rate limit is exhausted, with when it resets if they tell
+///
+/// The secondary rate limits come with a `Retry-After`, the primary one with `X-RateLimit-Remaining: 0` and
+/// `X-RateLimit-Reset` in seconds since the Unix epoch.
+pub(crate) fn rate_limit_reset(headers: &HeaderMap) -> Option<Option<SystemTime>> {
+    let seconds = |name: &str| headers
+	.get(name)
+	.and_then(|value| value.to_str().ok())
+	.and_then(|value| value.trim().parse::<u64>().ok());
+    if let Some(retry_after) = seconds("retry-after") {
+	return Some(Some(SystemTime::now() + Duration::from_secs(retry_after)));
+    }
+    (seconds("x-ratelimit-remaining") == Some(0))
+	.then(|| seconds("x-ratelimit-reset").map(|reset| SystemTime::UNIX_EPOCH + Duration::from_secs(reset)))
+}
+
+/// How long before the expiration of the token the fetches start warning about it
+#[cfg(feature = "tracing")]
+const TOKEN_EXPIRY_WARNING: Duration = Duration::from_secs(24 * 3_600);
+
+/// Parses the expiration dates of the tokens, `2024-05-01 12:34:56 UTC` or with a numeric offset like `+0200`
+pub(crate) fn parse_token_expiration(expiration: &str) -> Option<SystemTime> {
+    let mut fields = expiration.split(' ');
+    let (date, time, zone) = (fields.next()?, fields.next()?, fields.next()?);
+    let utc = parse_timestamp(&format!("{date}T{time}Z"))?;
+    if zone == "UTC" {
+	return Some(utc);
+    }
+    let (sign, offset) = zone.split_at_checked(1)?;
+    let (hours, minutes) = offset.split_at_checked(2)?;
+    let offset = Duration::from_secs(hours.parse::<u64>().ok()? * 3_600 + minutes.parse::<u64>().ok()? * 60);
+    // the local time is ahead of UTC by a positive offset
+    match sign {
+	"+"	=> utc.checked_sub(offset),
+	"-"	=> utc.checked_add(offset),
+	_	=> None,
+    }
+}
+
+/// The body of `response`, failing as soon as it's longer than `limit` (whatever its `content-length` says)
+async fn read_limited(mut response: reqwest::Response, limit: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Some(content_length) = response.content_length()
+	&& content_length > limit
+    {
+	return Err(Box::new(PrivUpdaterError::ApiResponseTooLarge { bytes_read: 0, limit }));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+	body.extend_from_slice(&chunk);
+	if body.len() as u64 > limit {
+	    return Err(Box::new(PrivUpdaterError::ApiResponseTooLarge { bytes_read: body.len() as u64, limit }));
+	}
+    }
+    Ok( body )
+}
+
+/// Parses the UTC timestamps of the GitHub API (`2024-05-01T12:34:56Z`), which are always in this format
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let fields = |s: &str, separator| s.split(separator).map(str::parse::<u64>).collect::<Result<Vec<_>, _>>().ok();
+    let (&[year, month, day], &[hour, minute, second]) = (fields(date, '-')?.as_slice(), fields(time, ':')?.as_slice()) else {
+	return None;
+    };
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+	return None;
+    }
+    // days since the epoch of the proleptic Gregorian calendar, with the years starting in March
+    let (year, month) = if month > 2 { (year, month - 3) } else { (year - 1, month + 9) };
+    let days = year * 365 + year / 4 - year / 100 + year / 400 + (153 * month + 2) / 5 + day - 1 - 719_468;
+    Some( SystemTime::UNIX_EPOCH + Duration::from_secs(days * 86_400 + hour * 3_600 + minute * 60 + second) )
+}
+
+/// GitHub announces the end of an API version through the `Deprecation` and `Sunset` headers (RFC 8594)
+#[cfg(feature = "tracing")]
+fn warn_on_api_sunset(headers: &HeaderMap) {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let request_id = header("x-github-request-id").unwrap_or_default();
+
+    if let Some(date) = header("sunset") {
+	tracing::warn!(sunset_date = %date, request_id, "GitHub API version will be sunset on this date; update priv_tauri_updater");
+    } else if let Some(deprecation) = header("deprecation") {
+	tracing::warn!(deprecation = %deprecation, request_id, "GitHub API version is deprecated; update priv_tauri_updater");
+    }
+}
+
+/// Runs [`PrivUpdater::check_release`] every `interval`, until the updater is dropped
+pub(crate) async fn check_periodically(updater: std::sync::Weak<PrivUpdater>, interval: Duration) {
+    let mut checks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    checks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+	checks.tick().await;
+	let Some(updater) = updater.upgrade() else {
+	    return;
+	};
+	if let Err(_e) = updater.check_release().await {
+	    #[cfg(feature = "tracing")]
+	    tracing::warn!(error = %_e, "failed to check for a new release");
+	}
+    }
+}
+
+/// The release currently served, shared by the server and [`PrivUpdater::reload_assets`]
+pub(crate) struct AssetStore {
+    #[cfg(not(feature = "arc-swap"))]
+    release:	std::sync::RwLock<Arc<Release>>,
+    #[cfg(feature = "arc-swap")]
+    release:	arc_swap::ArcSwap<Release>,
+}
+
+impl AssetStore {
+    pub(crate) fn new(release: Release) -> Self {
+	Self {
+	    #[cfg(not(feature = "arc-swap"))]
+	    release:	std::sync::RwLock::new(Arc::new(release)),
+	    #[cfg(feature = "arc-swap")]
+	    release:	arc_swap::ArcSwap::from_pointee(release),
+	}
+    }
+    pub(crate) fn load(&self) -> Arc<Release> {
+	#[cfg(not(feature = "arc-swap"))]
+	return self.release.read().unwrap().clone();
+	#[cfg(feature = "arc-swap")]
+	return self.release.load_full();
+    }
+    pub(crate) fn replace(&self, release: Release) {
+	#[cfg(not(feature = "arc-swap"))]
+	{ *self.release.write().unwrap() = Arc::new(release); }
+	#[cfg(feature = "arc-swap")]
+	self.release.store(Arc::new(release));
+    }
+}
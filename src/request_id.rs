@@ -0,0 +1,41 @@
+//! `X-Request-ID` of every response, see [`PrivUpdaterBuilder::request_id_generator`] (requires the `request-id` feature)
+
+use super::*;
+use warp::http::{ HeaderValue as WarpHeaderValue, Request };
+
+/// Generates the request IDs, shared by all the connections of the server
+pub(crate) type RequestIdGenerator = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// The default generator: a random UUID v4
+pub(crate) fn uuid_v4() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// A 21 characters nanoid, shorter than a UUID (requires the `nanoid` feature)
+///
+/// Pass it to [`PrivUpdaterBuilder::request_id_generator`] to use it instead of the UUID v4 default.
+#[cfg(feature = "nanoid")]
+pub fn nanoid_request_id() -> String {
+    nanoid::nanoid!()
+}
+
+/// Calls `service` with a newly generated request ID, which is sent back as the `X-Request-ID` response header
+///
+/// With the `tracing` feature, the request is handled in a `request` span recording the ID as `request_id`.
+pub(crate) fn call_with_request_id<S>(mut service: S, request: Request<Body>, generator: &RequestIdGenerator) -> impl Future<Output = Result<Response<Body>, Infallible>> + use<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+{
+    let request_id = generator();
+    let response = service.call(request);
+    #[cfg(feature = "tracing")]
+    let response = tracing::Instrument::instrument(response, tracing::info_span!("request", request_id = %request_id));
+    async move {
+	let mut response = response.await?;
+	// a custom generator could return anything, in which case the response goes without the header
+	if let Ok(value) = WarpHeaderValue::from_str(&request_id) {
+	    response.headers_mut().insert("x-request-id", value);
+	}
+	Ok(response)
+    }
+}
@@ -0,0 +1,180 @@
+//! Hooks run when the update server shuts down, see [`PrivUpdaterBuilder::on_shutdown`]
+
+use futures_util::future::BoxFuture;
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// What happens to the open connections when the update server shuts down, see [`PrivUpdaterBuilder::shutdown_mode`](crate::PrivUpdaterBuilder::shutdown_mode)
+///
+/// Serialized as `"immediate"`, `{ "drain": 30000 }` (in milliseconds) or `"wait_for_completion"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownMode {
+    /// Drops the connections right away, cutting off the responses in flight
+    Immediate,
+    /// Lets the responses in flight finish for at most the given duration, then drops the remaining connections
+    Drain(#[serde(with = "milliseconds")] Duration),
+    /// Waits for every response in flight to finish, however long it takes
+    #[default]
+    WaitForCompletion,
+}
+
+impl ShutdownMode {
+    /// How long the connections may stay open after the shutdown signal, `None` for as long as they need
+    pub(crate) fn drain_timeout(self) -> Option<Duration> {
+	match self {
+	    Self::Immediate		=> Some(Duration::ZERO),
+	    Self::Drain(timeout)	=> Some(timeout),
+	    Self::WaitForCompletion	=> None,
+	}
+    }
+}
+
+/// The drain timeout in milliseconds, like the durations of [`PrivUpdaterConfig`](crate::PrivUpdaterConfig)
+mod milliseconds {
+    use serde::{ Deserialize, Deserializer, Serializer };
+    use std::time::Duration;
+
+    pub(super) fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+    }
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+	u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Runs the hooks once the shutdown signal fires, or when dropped if the server future is cancelled before that
+pub(crate) struct ShutdownHooks {
+    pub(crate) sync_hook:	Option<Box<dyn FnOnce() + Send>>,
+    pub(crate) async_hook:	Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    pub(crate) async_timeout:	Duration,
+    /// See [`PrivUpdaterBuilder::publish_url_to_file`](crate::PrivUpdaterBuilder::publish_url_to_file), removed first
+    pub(crate) url_file:	Option<PathBuf>,
+}
+
+impl Default for ShutdownHooks {
+    fn default() -> Self {
+	Self {
+	    sync_hook:		None,
+	    async_hook:		None,
+	    async_timeout:	Duration::from_secs(5),
+	    url_file:		None,
+	}
+    }
+}
+
+impl ShutdownHooks {
+    pub(crate) async fn run(&mut self) {
+	self.remove_url_file();
+	if let Some(hook) = self.sync_hook.take() {
+	    hook();
+	}
+	if let Some(hook) = self.async_hook.take() {
+	    let _ = tokio::time::timeout(self.async_timeout, hook).await;
+	}
+    }
+}
+
+impl ShutdownHooks {
+    fn remove_url_file(&mut self) {
+	if let Some(path) = self.url_file.take() {
+	    let _ = std::fs::remove_file(path);
+	}
+    }
+}
+
+impl Drop for ShutdownHooks {
+    fn drop(&mut self) {
+	self.remove_url_file();
+	if let Some(hook) = self.sync_hook.take() {
+	    hook();
+	}
+	// can't be awaited here, so it runs detached (if there's still a runtime to run it on)
+	if let Some(hook) = self.async_hook.take()
+	    && let Ok(runtime) = tokio::runtime::Handle::try_current()
+	{
+	    runtime.spawn(tokio::time::timeout(self.async_timeout, hook));
+	}
+    }
+}
+
+/// How many times the server is restarted after stopping on its own, see [`supervise`]
+const MAX_RESTARTS: u32 = 3;
+
+/// Between the attempts to restart the server, when binding its address fails
+const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+/// The sender of a server's shutdown signal, and the server
+pub(crate) type RunningServer = (oneshot::Sender<()>, BoxFuture<'static, ()>);
+
+/// Runs `server` until `shutdown` fires (or its sender is dropped), then runs the `hooks` and shuts the server down
+///
+/// hyper stops the server without being asked to when accepting a connection fails, it's then started again
+/// with `restart`, at most [`MAX_RESTARTS`] times. The hooks also run once it's given up on, or when dropped
+/// if this future is cancelled.
+pub(crate) async fn supervise(
+    mut server:		RunningServer,
+    mut shutdown:	oneshot::Receiver<()>,
+    mut restart:	impl FnMut() -> Result<RunningServer, String>,
+    mut hooks:		ShutdownHooks,
+) {
+    let mut restarts = 0;
+    loop {
+	let (signal, mut running) = server;
+	if let futures_util::future::Either::Left(_) = futures_util::future::select(&mut shutdown, &mut running).await {
+	    hooks.run().await;
+	    let _ = signal.send(());
+	    running.await;
+	    return;
+	}
+	server = loop {
+	    if restarts == MAX_RESTARTS {
+		#[cfg(feature = "tracing")]
+		tracing::error!(restarts, "server exited unexpectedly; giving up");
+		hooks.run().await;
+		return;
+	    }
+	    restarts += 1;
+	    #[cfg(feature = "tracing")]
+	    tracing::error!(restart = restarts, "server exited unexpectedly; attempting restart");
+	    match restart() {
+		Ok(server)	=> break server,
+		Err(_e)		=> {
+		    #[cfg(feature = "tracing")]
+		    tracing::error!(error = %_e, "failed to restart the server");
+		    tokio::time::sleep(RESTART_DELAY).await;
+		},
+	    }
+	};
+    }
+}
+
+/// Spawns the connections of the server, so that they can be closed once the drain timeout elapsed
+///
+/// `hyper` runs every connection in its own task, which outlives the server future otherwise.
+#[derive(Clone)]
+pub(crate) struct ConnectionExecutor {
+    pub(crate) close:	tokio::sync::watch::Receiver<bool>,
+    /// see [`PrivUpdaterBuilder::keep_alive_timeout`](crate::PrivUpdaterBuilder::keep_alive_timeout)
+    pub(crate) idle_timeout:	Option<Duration>,
+}
+
+impl<F: Future<Output = ()> + Send + 'static> warp::hyper::rt::Executor<F> for ConnectionExecutor {
+    fn execute(&self, connection: F) {
+	let mut close = self.close.clone();
+	let idle_timeout = self.idle_timeout;
+	tokio::spawn(async move {
+	    let closed = close.wait_for(|close| *close);
+	    let connection = match idle_timeout {
+		Some(timeout)	=> futures_util::future::Either::Left(crate::keep_alive::close_when_idle(connection, timeout)),
+		None		=> futures_util::future::Either::Right(connection),
+	    };
+	    futures_util::future::select(std::pin::pin!(connection), std::pin::pin!(closed)).await;
+	});
+    }
+}
@@ -14,4 +14,816 @@ async fn updater_create_error() {
 	    None::<([u8; 4], u16)>
         ).await.is_err()
     );
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn updater_create_success() {
+    let release_info = r#"{"tag_name":"v1.2.0","published_at":"2024-05-01T12:34:56Z","assets":[
+	{"name":"latest.json","url":"https://api.github.com/repos/ErikMach/priv_tauri_updater/releases/assets/1","browser_download_url":"https://github.com/ErikMach/priv_tauri_updater/releases/download/v1.2.0/latest.json"},
+	{"name":"app.msi","url":"https://api.github.com/repos/ErikMach/priv_tauri_updater/releases/assets/2","browser_download_url":"https://github.com/ErikMach/priv_tauri_updater/releases/download/v1.2.0/app.msi"}
+    ]}"#;
+    let github = warp::path::end().map(|| "{}")
+	.or(warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest").map(move || release_info));
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+
+    let updater = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.build()
+	.await
+	.unwrap();
+    assert_eq!(updater.release_tag(), "v1.2.0");
+    assert_eq!(updater.asset_names().len(), 2);
+    assert_eq!(updater.asset_store.load().download_url_base, "https://github.com/ErikMach/priv_tauri_updater/releases/download/v1.2.0");
+}
+#[test]
+fn manifest_urls_rewritten() {
+    let manifest = br#"{"version":"1.2.0","notes":"see https://github.com/a/b/releases/download/v1.2.0","platforms":{"windows-x86_64":{"signature":"sig","url":"https://github.com/a/b/releases/download/v1.2.0/app.msi"}},"mirrors":[["https://github.com/a/b/releases/download/v1.2.0/app.zip",3,null,true]]}"#;
+    let rewritten = manifest::rewrite_urls(manifest, "https://github.com/a/b/releases/download/v1.2.0", "http://127.0.0.1:7748").unwrap();
+    assert_eq!(
+	String::from_utf8(rewritten).unwrap(),
+	r#"{"version":"1.2.0","notes":"see http://127.0.0.1:7748","platforms":{"windows-x86_64":{"signature":"sig","url":"http://127.0.0.1:7748/app.msi"}},"mirrors":[["http://127.0.0.1:7748/app.zip",3,null,true]]}"#
+    );
+    assert!(manifest::rewrite_urls(b"{\"url\": ", "a", "b").is_err());
+}
+#[test]
+fn config_round_trip() {
+    let config = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "ghp_token")
+	.server_addr(([127, 0, 0, 1], 8080))
+	.warp_log(true, "update_server")
+	.max_assets(10)
+	.verify_checksums(ChecksumAlgorithm::Sha256)
+	.shutdown_hook_timeout(std::time::Duration::from_millis(1500))
+	.shutdown_mode(ShutdownMode::Drain(std::time::Duration::from_millis(2500)))
+	.path_prefix_strip("/v1/")
+	.config();
+    let json = serde_json::to_string(&config).unwrap();
+    assert!(!json.contains("ghp_token"));
+    assert!(json.contains(r#""shutdown_mode":{"drain":2500}"#));
+    assert_eq!(serde_json::from_str::<PrivUpdaterConfig>(&json).unwrap(), config);
+    assert_eq!(config.to_builder("ghp_token").config(), config);
+
+    let partial: PrivUpdaterConfig = serde_json::from_str(r#"{ "gh_account_name": "ErikMach", "gh_repo_name": "priv_tauri_updater" }"#).unwrap();
+    assert_eq!(partial, PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "").config());
+}
+
+#[test]
+fn public_keys_injected() {
+    let inject = |public_keys: PublicKeys, manifest: &str| public_keys.inject(manifest.as_bytes()).map(|manifest| String::from_utf8(manifest.unwrap()).unwrap());
+    assert_eq!(inject(PublicKeys::default(), r#"{"version":"1.0.0"}"#), None);
+    assert_eq!(
+	inject(PublicKeys { key: Some("new".into()), additional: vec!["next".into()] }, r#"{"version":"1.0.0","pub_key":"old","pub_keys":["old"]}"#).unwrap(),
+	r#"{"pub_key":"new","pub_keys":["old","next"],"version":"1.0.0"}"#,
+    );
+    assert_eq!(
+	inject(PublicKeys { key: None, additional: vec!["next".into()] }, r#"{"version":"1.0.0"}"#).unwrap(),
+	r#"{"version":"1.0.0"}"#,
+    );
+}
+
+/// Serves `manifest` from a local server and returns what `get_latest_json` makes of it
+async fn rewrite_latest_json(manifest: &'static str, download_url_base: &str) -> String {
+    let (upstream_addr, upstream) = warp::serve(warp::any().map(move || manifest)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(upstream);
+    #[cfg_attr(not(feature = "middleware"), allow(clippy::useless_conversion))]
+    let client: HttpClient = Client::new().into();
+    let rewritten = get_latest_json(&client, &format!("http://{upstream_addr}/latest.json"), download_url_base, "http://127.0.0.1:7748", &PublicKeys::default())
+	.await
+	.unwrap();
+    String::from_utf8(rewritten).unwrap()
+}
+
+#[tokio::test]
+async fn latest_json_url_replaced() {
+    assert_eq!(
+	rewrite_latest_json(
+	    r#"{"version":"1.0.0","platforms":{"windows-x86_64":{"url":"https://github.com/a/b/releases/download/v1.0.0/app.msi"}}}"#,
+	    "https://github.com/a/b/releases/download/v1.0.0",
+	).await,
+	r#"{"version":"1.0.0","platforms":{"windows-x86_64":{"url":"http://127.0.0.1:7748/app.msi"}}}"#,
+    );
+}
+
+#[tokio::test]
+async fn latest_json_every_url_replaced() {
+    assert_eq!(
+	rewrite_latest_json(
+	    r#"{"platforms":{"windows-x86_64":{"url":"https://github.com/a/b/releases/download/v1.0.0/app.msi"},"darwin-aarch64":{"url":"https://github.com/a/b/releases/download/v1.0.0/app.tar.gz"}}}"#,
+	    "https://github.com/a/b/releases/download/v1.0.0",
+	).await,
+	r#"{"platforms":{"windows-x86_64":{"url":"http://127.0.0.1:7748/app.msi"},"darwin-aarch64":{"url":"http://127.0.0.1:7748/app.tar.gz"}}}"#,
+    );
+}
+
+#[tokio::test]
+async fn latest_json_partial_match_kept() {
+    assert_eq!(
+	rewrite_latest_json(
+	    r#"{"url":"https://github.com/a/b/releases/download/v1.0/app.msi","previous":"https://github.com/a/b/releases/download/v1.0.1/app.msi"}"#,
+	    "https://github.com/a/b/releases/download/v1.0",
+	).await,
+	r#"{"url":"http://127.0.0.1:7748/app.msi","previous":"https://github.com/a/b/releases/download/v1.0.1/app.msi"}"#,
+    );
+}
+
+#[tokio::test]
+async fn latest_json_special_characters_in_base() {
+    assert_eq!(
+	rewrite_latest_json(
+	    r#"{"url":"https://github.com/a/b.c/releases/download/v1.0.0+build.1/app.msi","other":"https://github.com/a/bxc/releases/download/v1x0x0+build.1/app.msi"}"#,
+	    "https://github.com/a/b.c/releases/download/v1.0.0+build.1",
+	).await,
+	r#"{"url":"http://127.0.0.1:7748/app.msi","other":"https://github.com/a/bxc/releases/download/v1x0x0+build.1/app.msi"}"#,
+    );
+}
+
+#[tokio::test]
+async fn latest_json_without_matching_url_unchanged() {
+    let manifest = r#"{"version":"1.0.0","notes":"no download here","platforms":{"linux-x86_64":{"url":"https://example.com/app.AppImage"}}}"#;
+    assert_eq!(rewrite_latest_json(manifest, "https://github.com/a/b/releases/download/v1.0.0").await, manifest);
+}
+
+#[test]
+fn github_timestamps_parsed() {
+    let secs = |timestamp| release::parse_timestamp(timestamp).map(|time| time.duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap().as_secs());
+    assert_eq!(secs("1970-01-01T00:00:00Z"), Some(0));
+    assert_eq!(secs("2000-02-29T00:00:00Z"), Some(951_782_400));
+    assert_eq!(secs("2024-05-01T12:34:56Z"), Some(1_714_566_896));
+    assert_eq!(secs("2100-12-31T23:59:59Z"), Some(4_133_980_799));
+    assert_eq!(secs("2024-05-01T12:34:56+02:00"), None);
+    assert_eq!(secs("2024-13-01T00:00:00Z"), None);
+    assert_eq!(secs("yesterday"), None);
+}
+
+#[test]
+fn updater_is_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PrivUpdater>();
+    assert_send_sync::<UpdateServerHandle>();
+}
+
+#[test]
+fn builder_from_env_vars() {
+    let from_vars = |extra: &[(&'static str, &'static str)]| {
+	let mut vars = HashMap::from([("PRIV_UPDATER_GH_ACCOUNT", "ErikMach"), ("PRIV_UPDATER_GH_REPO", "priv_tauri_updater"), ("PRIV_UPDATER_GH_TOKEN", "ghp_token")]);
+	vars.extend(extra.iter().copied());
+	PrivUpdaterBuilder::from_vars(|name| vars.get(name).map(|value| value.to_string()).filter(|value| !value.is_empty()))
+    };
+
+    let config = from_vars(&[]).unwrap().config();
+    assert_eq!((config.gh_account_name.as_str(), config.max_connections), ("ErikMach", None));
+    let config = from_vars(&[("PRIV_UPDATER_MAX_CONNECTIONS", "50"), ("PRIV_UPDATER_SHUTDOWN_DRAIN_SECONDS", "30")]).unwrap().config();
+    assert_eq!((config.max_connections, config.shutdown_mode), (Some(50), ShutdownMode::Drain(Duration::from_secs(30))));
+    assert_eq!(
+	from_vars(&[("PRIV_UPDATER_MAX_CONNECTIONS", "abc")]).err().unwrap().to_string(),
+	"PRIV_UPDATER_MAX_CONNECTIONS must be a positive integer, got 'abc'",
+    );
+    assert!(from_vars(&[("PRIV_UPDATER_MAX_CONNECTIONS", "0")]).is_err());
+    assert!(from_vars(&[("PRIV_UPDATER_SHUTDOWN_DRAIN_SECONDS", "-1")]).is_err());
+    assert!(matches!(from_vars(&[("PRIV_UPDATER_GH_TOKEN", "")]), Err(PrivUpdaterError::MissingEnvVar("PRIV_UPDATER_GH_TOKEN"))));
+}
+
+#[tokio::test]
+async fn api_response_size_limited() {
+    let release_info = r#"{"tag_name":"v1.0.0","published_at":null,"assets":[{"name":"app.msi","url":"https://api.github.com/a","browser_download_url":"https://github.com/a/b/releases/download/v1.0.0/app.msi"}]}"#;
+    let (upstream_addr, upstream) = warp::serve(warp::any().map(move || release_info)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(upstream);
+    #[cfg_attr(not(feature = "middleware"), allow(clippy::useless_conversion))]
+    let client: HttpClient = Client::new().into();
+    let source = |max_response_bytes| ReleaseSource {
+	client:			client.clone(),
+	backend:		Arc::new(backends::GitHubBackend::new("a", "b", "token").unwrap().api_base(&format!("http://{upstream_addr}"))),
+	max_assets:		None,
+	use_browser_download_url:	false,
+	max_response_bytes,
+	download_url_override:	None,
+	token_expires_at:	Mutex::new(None),
+    };
+
+    assert_eq!(source(release_info.len() as u64).fetch().await.unwrap().tag_name, "v1.0.0");
+    let error = source(100).fetch().await.err().unwrap();
+    assert!(matches!(error.downcast_ref(), Some(PrivUpdaterError::ApiResponseTooLarge { limit: 100, .. })));
+}
+
+#[test]
+fn test_ipv6_url_formatting() {
+    use util::socket_addr_to_url;
+    assert_eq!(socket_addr_to_url(&"127.0.0.1:7748".parse().unwrap(), "http"), "http://127.0.0.1:7748");
+    assert_eq!(socket_addr_to_url(&"127.0.0.1:7748".parse().unwrap(), "https"), "https://127.0.0.1:7748");
+    // not `http://::1:7748`, the port would be read as part of the address
+    assert_eq!(socket_addr_to_url(&"[::1]:7748".parse().unwrap(), "http"), "http://[::1]:7748");
+    assert_eq!(socket_addr_to_url(&"[::1]:7748".parse().unwrap(), "https"), "https://[::1]:7748");
+    assert_eq!(socket_addr_to_url(&"[2001:db8::42]:443".parse().unwrap(), "https"), "https://[2001:db8::42]:443");
+}
+
+#[tokio::test]
+async fn github_requests_rate_limited() {
+    let limiter = rate_limit::GlobalRateLimiter::new(60);
+    for _ in 0..30 {
+	assert!(limiter.try_acquire(2).await.is_ok());
+    }
+    let retry_after = limiter.try_acquire(1).await.unwrap_err();
+    assert!(retry_after > Duration::from_millis(900) && retry_after <= Duration::from_secs(1));
+
+    let reply = rate_limit::too_many_requests(Duration::from_millis(1_200));
+    assert_eq!(reply.status(), warp::http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(reply.headers()[warp::http::header::RETRY_AFTER], "2");
+}
+
+#[tokio::test]
+async fn interrupted_asset_download_resumed() {
+    // the first response breaks off after 4 of its 8 bytes, the others honour the Range header
+    let cdn = warp::header::optional::<String>("range").map(|range: Option<String>| match range.as_deref() {
+	Some("bytes=4-")	=> Response::builder()
+	    .status(warp::http::StatusCode::PARTIAL_CONTENT)
+	    .header("content-range", "bytes 4-7/8")
+	    .body(Body::from("efgh"))
+	    .unwrap(),
+	_		=> {
+	    let (mut sender, body) = Body::channel();
+	    tokio::spawn(async move {
+		sender.send_data("abcd".into()).await.unwrap();
+		// until the chunk is written, an abort discards it
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		sender.abort();
+	    });
+	    Response::builder().header("content-length", "8").body(body).unwrap()
+	},
+    });
+    let (cdn_addr, cdn) = warp::serve(cdn).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(cdn);
+    #[cfg_attr(not(feature = "middleware"), allow(clippy::useless_conversion))]
+    let client: HttpClient = Client::new().into();
+    let asset = AssetInfo { name: String::from("app.msi"), url: format!("http://{cdn_addr}/app.msi"), size: 8, ..Default::default() };
+
+    assert!(get_asset(&client, &asset, &[], None).await.is_err());
+    let (bytes, _) = get_asset(&client, &asset, &[], Some(2)).await.unwrap();
+    assert_eq!(bytes, b"abcdefgh");
+    // the release says the asset is longer
+    assert!(get_asset(&client, &AssetInfo { size: 9, ..asset }, &[], Some(2)).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn reconnect_backoff_capped() {
+    assert_eq!((reconnect::backoff(0), reconnect::backoff(3)), (Duration::from_millis(250), Duration::from_secs(2)));
+    assert_eq!((reconnect::backoff(32), reconnect::backoff(u8::MAX)), (Duration::from_secs(30), Duration::from_secs(30)));
+
+    // every response breaks off after 4 of its 8 bytes
+    let cdn = warp::any().map(|| {
+	let (mut sender, body) = Body::channel();
+	tokio::spawn(async move {
+	    sender.send_data("abcd".into()).await.unwrap();
+	    tokio::time::sleep(Duration::from_millis(50)).await;
+	    sender.abort();
+	});
+	Response::builder().header("content-length", "8").body(body).unwrap()
+    });
+    let (cdn_addr, cdn) = warp::serve(cdn).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(cdn);
+    #[cfg_attr(not(feature = "middleware"), allow(clippy::useless_conversion))]
+    let client: HttpClient = Client::new().into();
+    let asset = AssetInfo { name: String::from("app.msi"), url: format!("http://{cdn_addr}/app.msi"), size: 8, ..Default::default() };
+    let started = tokio::time::Instant::now();
+    assert!(get_asset(&client, &asset, &[], Some(40)).await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(40 * 30 + 60));
+}
+
+#[test]
+fn request_log_formats() {
+    let request = warp::http::Request::get("/latest.json?target=windows").header("user-agent", "tauri-updater").body(Body::empty()).unwrap();
+    let response = Response::builder().header("content-length", "1234").body(Body::empty()).unwrap();
+    let log_entry = |format| Arc::new(AccessLog { target: None, format, file: None }).start(&request, ([127, 0, 0, 1], 50_000).into());
+    let line = |format| log_entry(format).line(&response, Duration::from_millis(12));
+
+    let common = line(LogFormat::Common);
+    assert!(common.starts_with("127.0.0.1 - - ["));
+    assert!(common.ends_with("] \"GET /latest.json?target=windows HTTP/1.1\" 200 1234"));
+    assert!(line(LogFormat::Combined).ends_with("\" 200 1234 \"-\" \"tauri-updater\""));
+    assert_eq!(
+	line(LogFormat::Custom(String::from("{method} {path} {status} {elapsed_ms}ms {bytes} {remote_addr} {user_agent} {unknown}"))),
+	"GET /latest.json 200 12ms 1234 127.0.0.1 tauri-updater {unknown}",
+    );
+    assert_eq!(access_log::common_log_time(release::parse_timestamp("2024-02-29T23:59:58Z").unwrap()), "29/Feb/2024:23:59:58 +0000");
+    assert_eq!(access_log::common_log_time(release::parse_timestamp("1999-12-31T00:00:00Z").unwrap()), "31/Dec/1999:00:00:00 +0000");
+    assert_eq!(access_log::iso_8601_time(release::parse_timestamp("2024-02-29T23:59:58Z").unwrap()), "2024-02-29T23:59:58.000Z");
+
+    let entry = log_entry(LogFormat::Common).entry(&response, Duration::from_millis(12));
+    assert_eq!((entry.query.as_deref(), entry.bytes, entry.elapsed_ms), (Some("target=windows"), Some(1234), 12));
+    let csv = access_log::csv_line(&AccessLogEntry { user_agent: Some(String::from("tauri; \"v2\"")), ..entry }, ';');
+    assert!(csv.ends_with(";GET;/latest.json;target=windows;HTTP/1.1;200;1234;12;;\"tauri; \"\"v2\"\"\""), "{csv}");
+}
+
+#[tokio::test]
+async fn latest_json_not_modified() {
+    let release_info = warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest")
+	.and(warp::host::optional())
+	.map(|host: Option<warp::host::Authority>| format!(
+	    r#"{{"tag_name":"v1.2.0","published_at":null,"assets":[{{"name":"latest.json","url":"http://{}/assets/1","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/latest.json"}}]}}"#,
+	    host.unwrap(),
+	));
+    let github = warp::path::end().map(|| String::from("{}"))
+	.or(release_info)
+	.unify()
+	.or(warp::path!("assets" / "1")
+	    .and(warp::header::optional::<String>("if-none-match"))
+	    .map(|etag: Option<String>| match etag.as_deref() {
+		Some("\"v1\"")	=> Response::builder().status(warp::http::StatusCode::NOT_MODIFIED).header("etag", "\"v1\"").body(Body::empty()).unwrap(),
+		_		=> Response::builder().header("etag", "\"v1\"").body(Body::from(r#"{"version":"1.2.0"}"#)).unwrap(),
+	    }));
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/latest.json", listener.local_addr().unwrap());
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.headers()[ETAG], "\"v1\"");
+    let response = Client::new().get(&url).header(IF_NONE_MATCH, "\"v1\"").send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[ETAG], "\"v1\"");
+    server.shutdown();
+}
+
+#[test]
+fn token_expirations_parsed() {
+    let utc = release::parse_timestamp("2024-05-01T12:34:56Z");
+    assert_eq!(release::parse_token_expiration("2024-05-01 12:34:56 UTC"), utc);
+    assert_eq!(release::parse_token_expiration("2024-05-01 14:34:56 +0200"), utc);
+    assert_eq!(release::parse_token_expiration("2024-05-01 05:34:56 -0700"), utc);
+    assert!(release::parse_token_expiration("2024-05-01T12:34:56Z").is_none());
+    assert!(release::parse_token_expiration("2024-05-01 12:34:56 CEST").is_none());
+}
+
+#[test]
+fn rate_limited_requests_recognized() {
+    let headers = |pairs: &[(&'static str, &'static str)]| pairs
+	.iter()
+	.map(|&(name, value)| (reqwest::header::HeaderName::from_static(name), reqwest::header::HeaderValue::from_static(value)))
+	.collect::<HeaderMap>();
+    let reset_at = release::rate_limit_reset(&headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1714566896")]));
+    assert_eq!(reset_at, Some(release::parse_timestamp("2024-05-01T12:34:56Z")));
+    assert_eq!(
+	PrivUpdaterError::GitHubRateLimited { reset_at: reset_at.unwrap() }.to_string(),
+	"GitHub API rate limit exceeded, it resets at 2024-05-01T12:34:56.000Z",
+    );
+    assert!(release::rate_limit_reset(&headers(&[("retry-after", "60")])).unwrap().unwrap() > std::time::SystemTime::now());
+    // a token lacking a permission, with requests left
+    assert_eq!(release::rate_limit_reset(&headers(&[("x-ratelimit-remaining", "4999"), ("x-ratelimit-reset", "1714566896")])), None);
+    assert_eq!(release::rate_limit_reset(&headers(&[])), None);
+}
+
+#[test]
+fn backend_releases_parsed() {
+    use backends::{ Backend, GitHubBackend, GitLabBackend, GiteaBackend };
+    let github = GitHubBackend::new("a", "b", "token").unwrap();
+    let release = github.parse_release(br#"{"tag_name":"v1.2.0","published_at":null,"assets":[
+	{"name":"app.msi","url":"https://api.github.com/repos/a/b/releases/assets/1","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/app.msi",
+	 "size":3,"content_type":"application/x-msi","label":"","updated_at":"2024-05-01T12:34:56Z"}
+    ]}"#).unwrap();
+    assert_eq!((release.assets[0].size, release.assets[0].content_type.as_str()), (3, "application/x-msi"));
+    assert_eq!(release.assets[0].label, None);
+
+    let gitlab = GitLabBackend::new("group/app", "glpat-token").unwrap().base_url("https://gitlab.example.com/");
+    assert_eq!(gitlab.latest_release_url(), "https://gitlab.example.com/api/v4/projects/group%2Fapp/releases/permalink/latest");
+    assert_eq!(gitlab.api_headers()["private-token"], "glpat-token");
+    let release = gitlab.parse_release(br#"{"tag_name":"v1.2.0","released_at":"2024-05-01T12:34:56Z","assets":{"count":1,"sources":[],"links":[
+	{"name":"app.msi","url":"https://gitlab.example.com/group/app/-/releases/v1.2.0/downloads/app.msi","direct_asset_url":"https://gitlab.example.com/api/v4/projects/1/packages/generic/app/1.2.0/app.msi"}
+    ]}}"#).unwrap();
+    assert_eq!(release.tag_name, "v1.2.0");
+    assert_eq!(release.assets, [AssetInfo {
+	name:			String::from("app.msi"),
+	url:			String::from("https://gitlab.example.com/api/v4/projects/1/packages/generic/app/1.2.0/app.msi"),
+	browser_download_url:	String::from("https://gitlab.example.com/group/app/-/releases/v1.2.0/downloads/app.msi"),
+	..Default::default()
+    }]);
+
+    let gitea = GiteaBackend::new("https://codeberg.org", "owner", "app", "token").unwrap();
+    assert_eq!(gitea.latest_release_url(), "https://codeberg.org/api/v1/repos/owner/app/releases/latest");
+    assert_eq!(gitea.download_headers()[AUTHORIZATION], "token token");
+    let release = gitea.parse_release(br#"{"tag_name":"v1.2.0","published_at":"2024-05-01T12:34:56Z","assets":[
+	{"id":1,"name":"app.msi","size":3,"browser_download_url":"https://codeberg.org/owner/app/releases/download/v1.2.0/app.msi"}
+    ]}"#).unwrap();
+    assert_eq!(release.assets[0].url, release.assets[0].browser_download_url);
+    assert_eq!(release.assets[0].size, 3);
+    assert!(gitea.parse_release(b"{}").is_err());
+}
+
+#[tokio::test]
+async fn keep_alive_connections_closed() {
+    use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+
+    let github = warp::path::end().map(|| String::from("{}"))
+	.or(warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest")
+	    .map(|| String::from(r#"{"tag_name":"v1.2.0","published_at":null,"assets":[]}"#)))
+	.unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.keep_alive_max_requests(2)
+	.keep_alive_timeout(Duration::from_millis(200))
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+    let request = b"GET /robots.txt HTTP/1.1\r\nhost: localhost\r\n\r\n";
+
+    // the connection is closed after the second response, which says so
+    let mut connection = tokio::net::TcpStream::connect(addr).await.unwrap();
+    connection.write_all(request).await.unwrap();
+    connection.write_all(request).await.unwrap();
+    let mut responses = String::new();
+    tokio::time::timeout(Duration::from_secs(5), connection.read_to_string(&mut responses)).await.unwrap().unwrap();
+    assert_eq!(responses.matches("HTTP/1.1 200 OK").count(), 2);
+    assert_eq!(responses.matches("connection: close").count(), 1);
+
+    // an idle connection is closed after the timeout
+    let mut connection = tokio::net::TcpStream::connect(addr).await.unwrap();
+    connection.write_all(request).await.unwrap();
+    let mut responses = String::new();
+    tokio::time::timeout(Duration::from_secs(5), connection.read_to_string(&mut responses)).await.unwrap().unwrap();
+    assert_eq!(responses.matches("HTTP/1.1 200 OK").count(), 1);
+    server.shutdown();
+}
+
+#[cfg(feature = "cache")]
+#[tokio::test]
+async fn assets_cached_on_disk() {
+    let dir = std::env::temp_dir().join(format!("priv-tauri-updater-test-{}", std::process::id()));
+    let cache = DiskCache::new(dir.clone(), Duration::from_secs(60)).unwrap();
+    let headers: ForwardedHeaders = vec![(reqwest::header::ETAG, HeaderValue::from_static("\"v1\""))];
+    cache.insert("v1.2.0", "app.msi", b"msi", &headers).await;
+    cache.insert("v1.2.0", "latest.json", b"{}", &headers).await;
+    cache.insert("v1.2.0", "..", b"escaped", &headers).await;
+
+    assert_eq!(cache.get("v1.2.0", "app.msi").await, Some((b"msi".to_vec(), headers)));
+    assert_eq!(cache.get("v1.3.0", "app.msi").await, None);
+    assert_eq!(cache.get("v1.2.0", "latest.json").await, None);
+    assert_eq!(cache.get("v1.2.0", "..").await, None);
+    assert_eq!(DiskCache::new(dir.clone(), Duration::ZERO).unwrap().get("v1.2.0", "app.msi").await, None);
+    cache.clear().await.unwrap();
+    assert_eq!(cache.get("v1.2.0", "app.msi").await, None);
+    assert!(dir.exists());
+    std::fs::remove_dir(dir).unwrap();
+}
+
+#[tokio::test]
+async fn dropped_download_closes_upstream_connection() {
+    let (closed_tx, closed_rx) = oneshot::channel();
+    let closed_tx = Arc::new(Mutex::new(Some(closed_tx)));
+    // sends a chunk every 10ms, until the connection is closed
+    let cdn = warp::any().map(move || {
+	let (mut sender, body) = Body::channel();
+	let closed_tx = closed_tx.clone();
+	tokio::spawn(async move {
+	    while sender.send_data(vec![0; 1024].into()).await.is_ok() {
+		tokio::time::sleep(Duration::from_millis(10)).await;
+	    }
+	    if let Some(closed_tx) = closed_tx.lock().unwrap().take() {
+		let _ = closed_tx.send(());
+	    }
+	});
+	Response::builder().header("content-length", "1000000000").body(body).unwrap()
+    });
+    let (cdn_addr, cdn) = warp::serve(cdn).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(cdn);
+    #[cfg_attr(not(feature = "middleware"), allow(clippy::useless_conversion))]
+    let client: HttpClient = Client::new().into();
+    let asset = AssetInfo { name: String::from("app.msi"), url: format!("http://{cdn_addr}/app.msi"), ..Default::default() };
+
+    let download = tokio::spawn(async move { get_asset(&client, &asset, &[], None).await.map(|_| ()).map_err(|e| e.to_string()) });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    download.abort();
+    tokio::time::timeout(Duration::from_secs(5), closed_rx).await.expect("the upstream connection is closed").unwrap();
+}
+
+#[tokio::test]
+async fn connections_beyond_limit_rejected() {
+    let (release_tx, release_rx) = oneshot::channel::<()>();
+    let release_rx = Arc::new(Mutex::new(Some(release_rx)));
+    let github_url = Arc::new(std::sync::OnceLock::<String>::new());
+    let release_url = github_url.clone();
+    // the asset download hangs until `release_tx` is dropped
+    let github = warp::path::end().map(|| Response::new(Body::from("{}")))
+	.or(warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest").map(move || Response::new(Body::from(format!(
+	    r#"{{"tag_name":"v1.2.0","published_at":null,"assets":[{{"name":"app.msi","url":"{}/assets/1","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/app.msi"}}]}}"#,
+	    release_url.get().unwrap(),
+	)))))
+	.unify()
+	.or(warp::path!("assets" / "1").then(move || {
+	    let release_rx = release_rx.lock().unwrap().take();
+	    async move {
+		if let Some(release_rx) = release_rx {
+		    let _ = release_rx.await;
+		}
+		Response::new(Body::from("msi"))
+	    }
+	}))
+	.unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    github_url.set(format!("http://{github_addr}")).unwrap();
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.max_connections(1)
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+
+    let client = Client::new();
+    let download = tokio::spawn(client.get(format!("http://{addr}/app.msi")).send());
+    while server.active_connections() == 0 {
+	tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let rejected = client.get(format!("http://{addr}/robots.txt")).send().await.unwrap();
+    assert_eq!(rejected.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(rejected.headers()[reqwest::header::RETRY_AFTER], "1");
+    assert_eq!(rejected.json::<serde_json::Value>().await.unwrap(), serde_json::json!({ "error": "too_many_connections", "limit": 1 }));
+
+    drop(release_tx);
+    assert_eq!(download.await.unwrap().unwrap().bytes().await.unwrap(), "msi");
+    assert_eq!(server.active_connections(), 0);
+    server.shutdown();
+}
+
+#[test]
+fn path_prefixes_stripped() {
+    let prefixes = [String::from("/api/v1"), String::from("/v1")];
+    assert_eq!(asset_filename("/latest.json", &prefixes, None), Some("latest.json"));
+    assert_eq!(asset_filename("/v1/latest.json", &prefixes, None), Some("latest.json"));
+    assert_eq!(asset_filename("/api/v1/app.msi", &prefixes, None), Some("app.msi"));
+    // an unknown prefix is looked up as an asset name, i.e. a 404
+    assert_eq!(asset_filename("/v2/latest.json", &prefixes, None), Some("v2"));
+    assert_eq!(asset_filename("/v1x/latest.json", &prefixes, None), Some("v1x"));
+    assert_eq!(asset_filename("/v1", &prefixes, None), Some("v1"));
+    assert_eq!(asset_filename("/v1/", &prefixes, None), None);
+    assert_eq!(PrivUpdaterBuilder::new("a", "b", "c").path_prefix_strip("v1").path_prefix_strip("/").config().path_prefixes, ["/v1"]);
+}
+
+#[tokio::test]
+async fn new_release_announced_then_served() {
+    let published = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let github_published = published.clone();
+    let github = warp::path::end().map(|| String::from("{}"))
+	.or(warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest").map(move || format!(
+	    r#"{{"tag_name":"{}","published_at":null,"assets":[]}}"#,
+	    if github_published.load(std::sync::atomic::Ordering::Relaxed) { "v1.3.0" } else { "v1.2.0" },
+	)))
+	.unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.release_check_interval(Duration::from_millis(50))
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+    let mut new_release = server.updater().new_release_receiver();
+    assert_eq!(*new_release.borrow_and_update(), "v1.2.0");
+
+    published.store(true, std::sync::atomic::Ordering::Relaxed);
+    tokio::time::timeout(Duration::from_secs(5), new_release.changed()).await.unwrap().unwrap();
+    assert_eq!(*new_release.borrow(), "v1.3.0");
+    assert_eq!(server.updater().release_tag(), "v1.2.0");
+
+    // the next request switches releases
+    let _ = reqwest::get(format!("http://{addr}/latest.json")).await.unwrap();
+    assert_eq!(server.updater().release_tag(), "v1.3.0");
+    assert!(!new_release.has_changed().unwrap());
+    server.shutdown();
+}
+
+#[tokio::test]
+async fn stalled_manifest_response_aborted() {
+    let reply = send_within(Response::new(Body::from(r#"{"version":"1.2.0"}"#)), Duration::from_millis(50), "latest.json");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(hyper::body::to_bytes(reply.into_body()).await.is_err());
+
+    let reply = send_within(Response::new(Body::from(r#"{"version":"1.2.0"}"#)), Duration::from_secs(5), "latest.json");
+    assert_eq!(hyper::body::to_bytes(reply.into_body()).await.unwrap(), r#"{"version":"1.2.0"}"#);
+}
+
+#[tokio::test]
+async fn assets_served_under_route_prefix() {
+    let release_info = warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest")
+	.and(warp::host::optional())
+	.map(|host: Option<warp::host::Authority>| format!(
+	    r#"{{"tag_name":"v1.2.0","published_at":null,"assets":[{{"name":"latest.json","url":"http://{}/assets/1","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/latest.json"}}]}}"#,
+	    host.unwrap(),
+	));
+    let github = warp::path::end().map(|| String::from("{}"))
+	.or(release_info)
+	.unify()
+	.or(warp::path!("assets" / "1").map(|| String::from(r#"{"url":"https://github.com/a/b/releases/download/v1.2.0/app.msi"}"#)))
+	.unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.route_prefix("/api//v1/update/")
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+    assert_eq!(server.tauri_endpoint(), format!("http://{addr}/api/v1/update/latest.json"));
+
+    let manifest = reqwest::get(server.tauri_endpoint()).await.unwrap().text().await.unwrap();
+    assert_eq!(manifest, format!(r#"{{"url":"http://{addr}/api/v1/update/app.msi"}}"#));
+    assert_eq!(reqwest::get(format!("http://{addr}/latest.json")).await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+    assert_eq!(reqwest::get(format!("http://{addr}/health")).await.unwrap().status(), reqwest::StatusCode::OK);
+    server.shutdown();
+}
+
+#[tokio::test]
+async fn server_restarted_after_unexpected_exit() {
+    let hooks_ran = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let hooks = |hooks_ran: &Arc<std::sync::atomic::AtomicBool>| {
+	let hooks_ran = hooks_ran.clone();
+	let mut hooks = ShutdownHooks::default();
+	hooks.sync_hook = Some(Box::new(move || hooks_ran.store(true, std::sync::atomic::Ordering::Relaxed)));
+	hooks
+    };
+    // stops on its own every time
+    let exits_right_away = || (oneshot::channel().0, Box::pin(async {}) as BoxFuture<'static, ()>);
+    let mut restarts = 0;
+    let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+    shutdown::supervise(exits_right_away(), shutdown_rx, || { restarts += 1; Ok(exits_right_away()) }, hooks(&hooks_ran)).await;
+    assert_eq!(restarts, 3);
+    assert!(hooks_ran.swap(false, std::sync::atomic::Ordering::Relaxed));
+
+    // stops once, then runs until shut down
+    let (stopped_tx, stopped_rx) = oneshot::channel::<()>();
+    let mut stopped_tx = Some(stopped_tx);
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let supervisor = tokio::spawn(shutdown::supervise(exits_right_away(), shutdown_rx, move || {
+	let (signal, signalled) = oneshot::channel::<()>();
+	let stopped_tx = stopped_tx.take().unwrap();
+	Ok((signal, Box::pin(async move { let _ = signalled.await; let _ = stopped_tx.send(()); }) as BoxFuture<'static, ()>))
+    }, hooks(&hooks_ran)));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!supervisor.is_finished());
+    shutdown_tx.send(()).unwrap();
+    supervisor.await.unwrap();
+    assert!(hooks_ran.load(std::sync::atomic::Ordering::Relaxed));
+    stopped_rx.await.unwrap();
+}
+
+#[tokio::test]
+async fn asset_path_patterns_matched() {
+    let patterns = [vec![], vec![String::from("windows")], vec![String::from("{platform}"), String::from("{arch}")]];
+    assert_eq!(asset_filename("/app.msi", &[], Some(&patterns)), Some("app.msi"));
+    assert_eq!(asset_filename("/windows/app.msi", &[], Some(&patterns)), Some("app.msi"));
+    assert_eq!(asset_filename("/linux/x86_64/app.AppImage", &[], Some(&patterns)), Some("app.AppImage"));
+    assert_eq!(asset_filename("/linux/app.AppImage", &[], Some(&patterns)), None);
+    assert_eq!(asset_filename("/v1/windows/app.msi", &[String::from("/v1")], Some(&patterns)), Some("app.msi"));
+    assert_eq!(asset_filename("/windows/app.msi", &[], None), Some("windows"));
+
+    let error = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.serve_at_paths(vec![String::from("{filename}"), String::from("windows/")])
+	.build()
+	.await
+	.err()
+	.unwrap();
+    assert!(matches!(error.downcast_ref(), Some(PrivUpdaterError::InvalidPathPattern(pattern)) if pattern == "windows/"));
+}
+
+#[tokio::test]
+async fn server_header_set() {
+    let release_info = warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest")
+	.map(|| r#"{"tag_name":"v1.2.0","published_at":null,"assets":[]}"#);
+    let github = warp::path::end().map(|| "{}").or(release_info).unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let server_header = |builder: PrivUpdaterBuilder| async move {
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	let server = builder
+	    .github_api_base(&format!("http://{github_addr}"))
+	    .route_prefix("/updates")
+	    .build()
+	    .await
+	    .unwrap()
+	    .serve_on_listener(listener)
+	    .await
+	    .unwrap();
+	let served = reqwest::get(format!("http://{addr}/health")).await.unwrap();
+	let rejected = reqwest::get(format!("http://{addr}/updates/missing.msi")).await.unwrap();
+	assert_eq!(rejected.status(), reqwest::StatusCode::NOT_FOUND);
+	// outside the route prefix, warp answers it
+	let unrouted = reqwest::get(format!("http://{addr}/missing.msi")).await.unwrap();
+	assert_eq!(unrouted.status(), reqwest::StatusCode::NOT_FOUND);
+	server.shutdown();
+	let value = |response: &reqwest::Response| response.headers().get(reqwest::header::SERVER).map(|value| value.to_str().unwrap().to_string());
+	assert_eq!(value(&served), value(&rejected));
+	assert_eq!(value(&served), value(&unrouted));
+	value(&served)
+    };
+    let builder = || PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token");
+
+    assert_eq!(server_header(builder()).await, Some(format!("priv-tauri-updater/{}", env!("CARGO_PKG_VERSION"))));
+    assert_eq!(server_header(builder().server_header("updates")).await.as_deref(), Some("updates"));
+    assert_eq!(server_header(builder().server_header("updates").hide_server_header(true)).await, None);
+    assert!(builder().server_header("bad\nvalue").build().await.is_err());
+}
+
+#[tokio::test]
+async fn checksum_mismatch_rejected() {
+    let release_info = warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest")
+	.and(warp::host::optional())
+	.map(|host: Option<warp::host::Authority>| format!(
+	    r#"{{"tag_name":"v1.2.0","published_at":null,"assets":[{{"name":"app.msi","url":"http://{host}/assets/1","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/app.msi"}},{{"name":"app.msi.sha256","url":"http://{host}/assets/2","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/app.msi.sha256"}}]}}"#,
+	    host = host.unwrap(),
+	));
+    let github = warp::path::end().map(|| String::from("{}"))
+	.or(release_info)
+	.unify()
+	.or(warp::path!("assets" / "1").map(|| String::from("msi")))
+	.unify()
+	.or(warp::path!("assets" / "2").map(|| format!("{}  app.msi", "0".repeat(64))))
+	.unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.verify_checksums(ChecksumAlgorithm::Sha256)
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+
+    let response = reqwest::get(format!("http://{addr}/app.msi")).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+    assert_eq!(
+	response.text().await.unwrap(),
+	r#"{"error":"checksum_error","context":"app.msi checksum","message":"checksum mismatch for 'app.msi'"}"#,
+    );
+    server.shutdown();
+}
+
+#[tokio::test]
+async fn upstream_error_status_rejected() {
+    let release_info = warp::path!("repos" / "ErikMach" / "priv_tauri_updater" / "releases" / "latest")
+	.and(warp::host::optional())
+	.map(|host: Option<warp::host::Authority>| format!(
+	    r#"{{"tag_name":"v1.2.0","published_at":null,"assets":[{{"name":"latest.json","url":"http://{host}/assets/1","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/latest.json"}},{{"name":"app.msi","url":"http://{host}/assets/2","browser_download_url":"https://github.com/a/b/releases/download/v1.2.0/app.msi"}}]}}"#,
+	    host = host.unwrap(),
+	));
+    let github = warp::path::end().map(|| String::from("{}"))
+	.or(release_info)
+	.unify()
+	.map(|body| warp::reply::with_status(body, warp::http::StatusCode::OK))
+	.or(warp::path!("assets" / u32).map(|_| warp::reply::with_status(String::from("upstream failure"), warp::http::StatusCode::INTERNAL_SERVER_ERROR)))
+	.unify();
+    let (github_addr, github) = warp::serve(github).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(github);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PrivUpdaterBuilder::new("ErikMach", "priv_tauri_updater", "mock_token")
+	.github_api_base(&format!("http://{github_addr}"))
+	.build()
+	.await
+	.unwrap()
+	.serve_on_listener(listener)
+	.await
+	.unwrap();
+
+    for filename in ["latest.json", "app.msi"] {
+	let response = reqwest::get(format!("http://{addr}/{filename}")).await.unwrap();
+	assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+	let body: serde_json::Value = response.json().await.unwrap();
+	assert_eq!(body["error"], "upstream_error");
+	assert!(body["context"].as_str().unwrap().starts_with(&format!("fetching asset '{filename}'")), "{body}");
+	assert!(body["message"].as_str().unwrap().contains("500"), "{body}");
+    }
+    server.shutdown();
+}
@@ -0,0 +1,123 @@
+//! Mutual TLS between the update server and the Tauri app, see [`PrivUpdaterBuilder::mtls`] (requires the `mtls` feature)
+//!
+//! Every accepted connection goes through a TLS handshake requiring a client certificate signed by one of the
+//! trusted client CAs. The CAs can be added to while the server runs, the handshakes started from then on
+//! use the new configuration.
+
+use super::*;
+use arc_swap::ArcSwap;
+use futures_util::stream::{ FuturesUnordered, StreamExt };
+use rustls::{
+    RootCertStore,
+    ServerConfig,
+    pki_types::{ CertificateDer, PrivateKeyDer, pem::PemObject },
+    server::WebPkiClientVerifier,
+};
+use tokio_rustls::{ Accept as Handshake, TlsAcceptor, server::TlsStream };
+use warp::hyper::server::accept::Accept;
+
+/// The server identity, the trusted client CAs and the TLS configuration derived from them
+pub(crate) struct ClientAuth {
+    cert_chain:	Vec<CertificateDer<'static>>,
+    key:	PrivateKeyDer<'static>,
+    client_cas:	Mutex<Vec<CertificateDer<'static>>>,
+    config:	ArcSwap<ServerConfig>,
+}
+
+impl ClientAuth {
+    pub(crate) fn new(cert_chain_pem: &[u8], key_pem: &[u8], client_ca_pem: &[u8]) -> Result<Self, Box<dyn Error>> {
+	let cert_chain = certificates(cert_chain_pem)?;
+	let key = PrivateKeyDer::from_pem_slice(key_pem)?;
+	let client_cas = certificates(client_ca_pem)?;
+	let config = server_config(&cert_chain, &key, &client_cas)?;
+	Ok( Self {
+	    cert_chain,
+	    key,
+	    client_cas:	Mutex::new(client_cas),
+	    config:	ArcSwap::from_pointee(config),
+	} )
+    }
+    /// Trusts the client certificates signed by the CAs of `client_ca_pem` too
+    pub(crate) fn add_client_ca(&self, client_ca_pem: &[u8]) -> Result<(), Box<dyn Error>> {
+	let mut client_cas = self.client_cas.lock().unwrap();
+	let mut added = client_cas.clone();
+	added.extend(certificates(client_ca_pem)?);
+	self.config.store(Arc::new(server_config(&self.cert_chain, &self.key, &added)?));
+	*client_cas = added;
+	Ok(())
+    }
+}
+
+fn certificates(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>, Box<dyn Error>> {
+    let certificates = CertificateDer::pem_slice_iter(pem).collect::<Result<Vec<_>, _>>()?;
+    if certificates.is_empty() {
+	return Err(Box::new(PrivUpdaterError::InvalidTlsConfig(String::from("no certificate in the PEM"))));
+    }
+    Ok( certificates )
+}
+
+fn server_config(cert_chain: &[CertificateDer<'static>], key: &PrivateKeyDer<'static>, client_cas: &[CertificateDer<'static>]) -> Result<ServerConfig, Box<dyn Error>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut roots = RootCertStore::empty();
+    for ca in client_cas {
+	roots.add(ca.clone())?;
+    }
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone()).build()?;
+    let mut config = ServerConfig::builder_with_provider(provider)
+	.with_safe_default_protocol_versions()?
+	.with_client_cert_verifier(verifier)
+	.with_single_cert(cert_chain.to_vec(), key.clone_key())?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok( config )
+}
+
+/// The TCP connections of `incoming`, once their TLS handshake succeeded
+///
+/// The handshakes run concurrently, a slow client doesn't hold back the others. Failed handshakes (e.g. without
+/// a trusted client certificate) only close their connection.
+pub(crate) struct TlsIncoming {
+    incoming:	AddrIncoming,
+    client_auth:	Arc<ClientAuth>,
+    handshakes:	FuturesUnordered<Handshake<AddrStream>>,
+}
+
+impl TlsIncoming {
+    pub(crate) fn new(incoming: AddrIncoming, client_auth: Arc<ClientAuth>) -> Self {
+	Self { incoming, client_auth, handshakes: FuturesUnordered::new() }
+    }
+}
+
+impl Accept for TlsIncoming {
+    type Conn	= TlsStream<AddrStream>;
+    type Error	= std::io::Error;
+
+    fn poll_accept(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+	let this = &mut *self;
+	let mut closed = false;
+	loop {
+	    match Pin::new(&mut this.incoming).poll_accept(cx) {
+		Poll::Ready(Some(Ok(stream)))	=> this.handshakes.push(TlsAcceptor::from(this.client_auth.config.load_full()).accept(stream)),
+		Poll::Ready(Some(Err(e)))	=> return Poll::Ready(Some(Err(e))),
+		Poll::Ready(None)		=> { closed = true; break; },
+		Poll::Pending			=> break,
+	    }
+	}
+	loop {
+	    match this.handshakes.poll_next_unpin(cx) {
+		Poll::Ready(Some(Ok(stream)))	=> return Poll::Ready(Some(Ok(stream))),
+		Poll::Ready(Some(Err(_e)))	=> {
+		    #[cfg(feature = "tracing")]
+		    tracing::debug!(error = %_e, "TLS handshake failed");
+		},
+		Poll::Ready(None)		=> return if closed { Poll::Ready(None) } else { Poll::Pending },
+		Poll::Pending			=> return Poll::Pending,
+	    }
+	}
+    }
+}
+
+impl ClientAddr for TlsStream<AddrStream> {
+    fn client_addr(&self) -> SocketAddr {
+	self.get_ref().0.remote_addr()
+    }
+}
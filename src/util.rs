@@ -0,0 +1,11 @@
+//! Small helpers shared by the modules
+
+use std::net::SocketAddr;
+
+/// `{scheme}://{addr}`, with an IPv6 address in brackets (`http://[::1]:7748`) as URLs require
+pub(crate) fn socket_addr_to_url(addr: &SocketAddr, scheme: &str) -> String {
+    match addr {
+	SocketAddr::V4(addr)	=> format!("{scheme}://{}:{}", addr.ip(), addr.port()),
+	SocketAddr::V6(addr)	=> format!("{scheme}://[{}]:{}", addr.ip(), addr.port()),
+    }
+}
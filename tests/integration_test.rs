@@ -0,0 +1,86 @@
+//! Serves a release of a mocked GitHub API end to end, without internet access
+
+use priv_tauri_updater::PrivUpdaterBuilder;
+use reqwest::{ Client, StatusCode };
+use std::time::Duration;
+use wiremock::{
+    Mock,
+    MockServer,
+    ResponseTemplate,
+    matchers::{ header, method, path },
+};
+
+const MSI: &[u8] = b"not really an msi";
+
+/// A GitHub release with a `latest.json` and an `.msi`, downloaded from `github`
+async fn mock_github() -> MockServer {
+    let github = MockServer::start().await;
+    let asset = |id: u32, name: &str| serde_json::json!({
+	"name":			name,
+	"url":			format!("{}/repos/test/repo/releases/assets/{id}", github.uri()),
+	"browser_download_url":	format!("https://github.com/test/repo/releases/download/v1.0.0/{name}"),
+	"size":			0,
+	"content_type":		"application/octet-stream",
+	"label":		"",
+	"updated_at":		"2024-05-01T12:34:56Z",
+    });
+    // the preflight check
+    Mock::given(method("GET")).and(path("/"))
+	.respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+	.mount(&github)
+	.await;
+    Mock::given(method("GET")).and(path("/repos/test/repo/releases/latest"))
+	.and(header("authorization", "Bearer test_token"))
+	.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+	    "tag_name":		"v1.0.0",
+	    "published_at":	"2024-05-01T12:34:56Z",
+	    "assets":		[asset(1, "latest.json"), asset(2, "app.msi")],
+	})))
+	.mount(&github)
+	.await;
+    Mock::given(method("GET")).and(path("/repos/test/repo/releases/assets/1"))
+	.and(header("accept", "application/octet-stream"))
+	.respond_with(ResponseTemplate::new(200).set_body_string(
+	    r#"{"version":"1.0.0","platforms":{"windows-x86_64":{"signature":"sig","url":"https://github.com/test/repo/releases/download/v1.0.0/app.msi"}}}"#,
+	))
+	.mount(&github)
+	.await;
+    Mock::given(method("GET")).and(path("/repos/test/repo/releases/assets/2"))
+	.and(header("accept", "application/octet-stream"))
+	.respond_with(ResponseTemplate::new(200).set_body_bytes(MSI))
+	.mount(&github)
+	.await;
+    github
+}
+
+#[tokio::test]
+async fn release_served_from_mocked_github() {
+    let github = mock_github().await;
+    let handle = PrivUpdaterBuilder::new("test", "repo", "test_token")
+	.github_api_base(&github.uri())
+	.port(0)
+	.build()
+	.await
+	.unwrap()
+	.serve_update()
+	.await
+	.unwrap();
+    let server = format!("http://127.0.0.1:{}", handle.bound_addr().port());
+    let client = Client::new();
+
+    let manifest = client.get(format!("{server}/latest.json")).send().await.unwrap();
+    assert_eq!(manifest.status(), StatusCode::OK);
+    let manifest: serde_json::Value = manifest.json().await.unwrap();
+    assert_eq!(manifest["platforms"]["windows-x86_64"]["url"], format!("{server}/app.msi"));
+
+    let msi = client.get(format!("{server}/app.msi")).send().await.unwrap();
+    assert_eq!(msi.status(), StatusCode::OK);
+    assert_eq!(msi.bytes().await.unwrap(), MSI);
+
+    let unknown = client.get(format!("{server}/app.dmg")).send().await.unwrap();
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+    handle.shutdown();
+    tokio::time::timeout(Duration::from_secs(5), handle).await.expect("the server stops after the shutdown");
+    assert!(client.get(format!("{server}/latest.json")).send().await.is_err());
+}